@@ -1,17 +1,157 @@
 import React, { useState, useEffect, useCallback, useRef, type FC } from 'react';
 import { decode } from '@msgpack/msgpack';
 
-/* MessagePack type */
-type MessagePack = {
-    pcm: Uint8Array;
-    bpm: number;
+/* MessagePack type (binary frame sent by the middle-server, tagged by `type`) */
+type MessagePack =
+    | { type: 'hello'; version: number }
+    | {
+          type: 'stream_header';
+          channels: number;
+          sample_rate: number;
+          bits_per_sample: number;
+          pcm_format: string;
+      }
+    | { type: 'audio_chunk'; start_frame: number; pcm: Uint8Array }
+    | {
+          type: 'analysis';
+          start_frame: number;
+          end_frame: number;
+          bpm: number | null;
+          confidence: number;
+          beats: Beat[];
+          tempo_curve: TempoPoint[];
+          meter: TimeSignature | null;
+          key: Key | null;
+          chords: Chord[];
+          hpss: Hpss | null;
+          stereo: Stereo | null;
+          grooves: Groove[];
+      }
+    | ({ type: 'section_change' } & Section)
+    | {
+          type: 'summary';
+          key: Key | null;
+          meter: TimeSignature | null;
+          sections: Section[];
+          tempo_curve: TempoPoint[];
+          groove: TrackGroove | null;
+      }
+    | ({ type: 'loudness' } & Loudness)
+    | { type: 'spectrum'; frame: number; bands: Uint8Array }
+    | ({ type: 'onset' } & Onset)
+    | {
+          type: 'pitch';
+          start_frame: number;
+          start_time: number;
+          hop_frames: number;
+          f0: number[];
+          voicing: number[];
+      }
+    | { type: 'error'; reason: string }
+    | { type: 'end_of_stream' };
+
+/* levels of a block of the stream (dB, at least -120) */
+type Loudness = {
+    start_frame: number;
+    end_frame: number;
+    rms: number[];
+    sample_peak: number[];
+    true_peak: number[];
+    momentary: number | null;
+    short_term: number | null;
+    clipping: boolean;
+};
+
+/* musical key (e.g. name: 'A minor', camelot: '8A') */
+type Key = { name: string; camelot: string; confidence: number };
+
+/* chord on the stream timeline (name: e.g. 'F#m', null: no chord) */
+type Chord = {
+    start_frame: number;
+    end_frame: number;
+    start_time: number;
+    end_time: number;
+    name: string | null;
+    confidence: number;
+};
+
+/* harmonic and percussive parts of a window (levels in dBFS, ratio: share of the percussive energy) */
+type Hpss = { harmonic_level: number; percussive_level: number; percussive_ratio: number };
+
+/* channels of a window (levels in dBFS; correlation and width: null for a mono stream) */
+type Stereo = { levels: number[]; correlation: number | null; width: number | null };
+
+/* swing (1.0: straight, 2.0: triplet) and deviation of each sixteenth note (ms, null: no onset) */
+type TrackGroove = { swing: number | null; subdivision: 8 | 16; deviations: (number | null)[] };
+type Groove = TrackGroove & {
+    bar: number;
+    start_frame: number;
+    end_frame: number;
+    start_time: number;
+    end_time: number;
+};
+
+/* beat on the stream timeline */
+type Beat = { frame: number; time: number; downbeat: boolean; bar: number; beat_in_bar: number };
+
+/* time signature (6/8 is counted in two dotted quarter beats) */
+type TimeSignature = '3/4' | '4/4' | '6/8';
+const BEATS_PER_BAR: Record<TimeSignature, number> = { '3/4': 3, '4/4': 4, '6/8': 2 };
+
+/* local tempo at a beat */
+type TempoPoint = { frame: number; time: number; bpm: number };
+
+/* pitch of the melody at a point of the stream (f0 in Hz, voicing: 0.0 to 1.0) */
+type PitchPoint = { time: number; f0: number; voicing: number };
+
+/* percussive hit on the stream timeline (strength: 0.0 to 1.0) */
+type Instrument = 'kick' | 'snare' | 'hi_hat';
+type Onset = { frame: number; time: number; strength: number; instrument: Instrument };
+
+/* section of the track (a section change has no end yet) */
+type SectionKind = 'intro' | 'build_up' | 'drop' | 'breakdown' | 'outro' | 'section';
+type Section = {
+    start_frame: number;
+    start_time: number;
+    end_frame?: number;
+    end_time?: number;
+    label: SectionKind;
+    confidence: number;
 };
 
 /* connection status types */
 type WebSocketReadyState = 'idle' | 'connecting' | 'connected' | 'disconnected';
 
 /* error type */
-type WebSocketError = 'decode' | 'play' | 'websocket' | 'invalid_audio_info';
+type WebSocketError = 'decode' | 'play' | 'websocket' | 'invalid_audio_info' | 'protocol';
+
+/* control protocol version */
+const PROTOCOL_VERSION = 1;
+
+/* tempo detector of the analysis (empty: the middle-server default) */
+type TempoDetectorKind = 'librosa' | 'native' | 'python';
+
+/* signal of the stream that is analyzed (empty: the mono downmix) */
+type ChannelMode = 'mono' | 'left' | 'right' | 'mid' | 'side';
+
+/* analysis options of the session (read by the middle-server) */
+type AnalysisOptions = {
+    tempo_detector?: TempoDetectorKind;
+    hpss?: boolean;
+    channel_mode?: ChannelMode;
+    spectrum_bands?: number;
+    spectrum_rate?: number;
+};
+
+/* spectrum settings requested from the middle-server */
+const SPECTRUM_BANDS = 32;
+const SPECTRUM_RATE = 30;
+
+/* control message type (JSON text frame sent by the client) */
+type ControlMessage =
+    | { version: number; type: 'hello' }
+    | { version: number; type: 'open'; track_id?: string; analysis?: AnalysisOptions }
+    | { version: number; type: 'accept' };
 
 /* AudioInfo type */
 type AudioInfo = {
@@ -46,10 +186,20 @@ const playRawPCM = (
 
         for (let i = 0; i < numSampleFrames; i++) {
             let value = 0;
-            if (info.bitsPerSample === 16) {
+            if (info.pcmFormat === 's16le') {
                 value = dataView.getInt16(pcmOffset, true);
                 channelData[i] = value / 32768;
-            } else if (info.bitsPerSample === 32 && info.pcmFormat === 'f32le') {
+            } else if (info.pcmFormat === 's24le') {
+                // 3 bytes little-endian, sign-extended from bit 23
+                value =
+                    dataView.getUint8(pcmOffset) |
+                    (dataView.getUint8(pcmOffset + 1) << 8) |
+                    (dataView.getInt8(pcmOffset + 2) << 16);
+                channelData[i] = value / 8388608;
+            } else if (info.pcmFormat === 's32le') {
+                value = dataView.getInt32(pcmOffset, true);
+                channelData[i] = value / 2147483648;
+            } else if (info.pcmFormat === 'f32le') {
                 value = dataView.getFloat32(pcmOffset, true);
                 channelData[i] = value;
             }
@@ -62,10 +212,47 @@ const playRawPCM = (
     source.connect(context.destination);
     source.start();
 };
+/* beat pulse of the visualizer */
+type BeatState = { pulse: boolean; downbeat: boolean; beatInBar: number };
+
+/* the beat pulse lasts this fraction of a beat period */
+const BEAT_PULSE_LENGTH = 0.15;
+
+/* the melody line shows this many seconds, and the frames that are voiced at least this likely */
+const MELODY_LENGTH = 5;
+const MIN_VOICING = 0.5;
+
+/* the number of chords the client remembers */
+const CHORD_HISTORY = 64;
+
+/* a hit lights its lamp for this many seconds */
+const ONSET_FLASH_LENGTH = 0.08;
+const INSTRUMENTS: Instrument[] = ['kick', 'snare', 'hi_hat'];
+
+/* format a level in dB (null: not measured yet) */
+const formatLevel = (level: number | null) => (level == null ? '-' : level.toFixed(1));
+
 /* useWebSocket return type */
 type UseWebSocketHook = {
     audioInfoState: AudioInfo | null;
-    bpmState: number;
+    bpmState: number | null;
+    confidenceState: number;
+    meterState: TimeSignature | null;
+    keyState: Key | null;
+    trackKeyState: Key | null;
+    tempoCurveState: TempoPoint[];
+    chordState: Chord | null;
+    hpssState: Hpss | null;
+    stereoState: Stereo | null;
+    grooveState: Groove | null;
+    trackGrooveState: TrackGroove | null;
+    sectionState: Section | null;
+    trackSectionsState: Section[];
+    loudnessState: Loudness | null;
+    spectrumState: Uint8Array | null;
+    beatState: BeatState;
+    hitState: Record<Instrument, number>;
+    melodyState: PitchPoint[];
     readyState: WebSocketReadyState;
     error: WebSocketError | null;
     connect: () => void;
@@ -73,7 +260,13 @@ type UseWebSocketHook = {
 };
 
 /* useWebSocket */
-const useWebSocket = (url: string): UseWebSocketHook => {
+const useWebSocket = (
+    url: string,
+    trackId: string,
+    tempoDetector: TempoDetectorKind | '',
+    hpss: boolean | null,
+    channelMode: ChannelMode | '',
+): UseWebSocketHook => {
     //* WebSocket *//
     // WebSocket instance
     const ws = useRef<WebSocket | null>(null);
@@ -90,7 +283,46 @@ const useWebSocket = (url: string): UseWebSocketHook => {
     const audioContext = useRef<AudioContext | null>(null);
 
     //* Animation *//
-    const [bpmState, setBpmState] = useState<number>(0);
+    // null: no steady tempo (e.g. silence)
+    const [bpmState, setBpmState] = useState<number | null>(null);
+    const [confidenceState, setConfidenceState] = useState<number>(0);
+    const [meterState, setMeterState] = useState<TimeSignature | null>(null);
+    // tempo curve so far (replaced by the curve of the whole track once the stream ends)
+    const [tempoCurveState, setTempoCurveState] = useState<TempoPoint[]>([]);
+    // key of the latest window, and of the whole track once the stream ends
+    const [keyState, setKeyState] = useState<Key | null>(null);
+    const [trackKeyState, setTrackKeyState] = useState<Key | null>(null);
+    // chord that is being played
+    const [chordState, setChordState] = useState<Chord | null>(null);
+    // harmonic and percussive parts of the latest window (null: not separated)
+    const [hpssState, setHpssState] = useState<Hpss | null>(null);
+    // channels of the latest window
+    const [stereoState, setStereoState] = useState<Stereo | null>(null);
+    // groove of the latest bar, and of the whole track once the stream ends
+    const [grooveState, setGrooveState] = useState<Groove | null>(null);
+    const [trackGrooveState, setTrackGrooveState] = useState<TrackGroove | null>(null);
+    // latest section change, and the sections of the whole track once the stream ends
+    const [sectionState, setSectionState] = useState<Section | null>(null);
+    const [trackSectionsState, setTrackSectionsState] = useState<Section[]>([]);
+    // latest levels of the stream
+    const [loudnessState, setLoudnessState] = useState<Loudness | null>(null);
+    // latest band levels (0: -100 dB, 255: 0 dB)
+    const [spectrumState, setSpectrumState] = useState<Uint8Array | null>(null);
+    const [beatState, setBeatState] = useState<BeatState>({ pulse: false, downbeat: false, beatInBar: 0 });
+    // recent pitch of the stream
+    const [melodyState, setMelodyState] = useState<PitchPoint[]>([]);
+    // strength of the hit that is lit on each lamp (0: off)
+    const [hitState, setHitState] = useState<Record<Instrument, number>>({ kick: 0, snare: 0, hi_hat: 0 });
+    // the stream time of the chunk that started playing last, and when it started
+    const streamClock = useRef<{ time: number; at: number } | null>(null);
+    // the latest beat reported by the analysis
+    const lastBeat = useRef<Beat | null>(null);
+    const bpmRef = useRef<number>(0);
+    const beatsPerBarRef = useRef<number>(4);
+    // the chords reported so far, in order (only the recent ones are kept)
+    const chords = useRef<Chord[]>([]);
+    // the onsets that have not been played yet
+    const pendingOnsets = useRef<Onset[]>([]);
 
     // event listeners setup function
     const setupEventListeners = useCallback(() => {
@@ -101,55 +333,63 @@ const useWebSocket = (url: string): UseWebSocketHook => {
 
         ws.current.binaryType = 'arraybuffer';
 
+        // send a control message as a JSON text frame
+        const send = (message: ControlMessage) => {
+            ws.current?.send(JSON.stringify(message));
+        };
+
         ws.current.onopen = () => {
             console.log('WebSocket connection established');
             setReadyState('connected');
-            //* step1: send open message *//
-            ws.current?.send('open');
+            //* step0: send hello message *//
+            send({ version: PROTOCOL_VERSION, type: 'hello' });
+            //* step1: send open message (with the selected track and analysis options) *//
+            send({
+                version: PROTOCOL_VERSION,
+                type: 'open',
+                ...(trackId ? { track_id: trackId } : {}),
+                analysis: {
+                    ...(tempoDetector ? { tempo_detector: tempoDetector } : {}),
+                    ...(hpss != null ? { hpss } : {}),
+                    ...(channelMode ? { channel_mode: channelMode } : {}),
+                    spectrum_bands: SPECTRUM_BANDS,
+                    spectrum_rate: SPECTRUM_RATE,
+                },
+            });
         };
 
         ws.current.onmessage = async (event: MessageEvent) => {
             /* binary data */
-            if (event.data instanceof ArrayBuffer) {
-                let message: MessagePack | null = null;
-                //* step6: received and decode MessagePack data *//
-                try {
-                    message = decode(event.data) as MessagePack;
-                    console.log('Received MessagePack data:', message);
-                    //* step7: set BPM *//
-                    setBpmState(message.bpm);
-                } catch (error) {
-                    console.error('Failed to decode MessagePack:', error);
-                    setError('decode');
-                }
-                //* step8: play PCM data *//
-                if (audioContext.current && audioInfoRef.current && message && message.pcm && message.pcm.length > 0) {
-                    try {
-                        // decodeAudioDataの代わりに、新しいヘルパー関数を呼び出す
-                        playRawPCM(audioContext.current, message.pcm, audioInfoRef.current);
-                    } catch (err) {
-                        console.error('Failed to play raw PCM data:', err);
-                        setError('play');
-                    }
-                }
+            if (!(event.data instanceof ArrayBuffer)) {
+                console.error('Received unexpected text data:', event.data);
+                setError('protocol');
+                return;
+            }
+            let message: MessagePack;
+            //* step6: received and decode MessagePack data *//
+            try {
+                message = decode(event.data) as MessagePack;
+            } catch (error) {
+                console.error('Failed to decode MessagePack:', error);
+                setError('decode');
+                return;
             }
-            /* test data */
-            if (typeof event.data === 'string') {
-                console.log('Received text data:', event.data);
-                //* step2: parse AudioInfo *//
-                /* Received data format: <channels> <sample_rate> <bits_per_sample> <pcm_format> */
-                const parts = event.data.split(' ');
-                if (parts.length === 4) {
+            switch (message.type) {
+                case 'hello':
+                    console.log('Server protocol version:', message.version);
+                    break;
+                case 'stream_header': {
+                    //* step2: parse AudioInfo *//
                     const audioInfo = {
-                        channel: parseInt(parts[0], 10),
-                        sampleRate: parseInt(parts[1], 10),
-                        bitsPerSample: parseInt(parts[2], 10),
-                        pcmFormat: parts[3],
+                        channel: message.channels,
+                        sampleRate: message.sample_rate,
+                        bitsPerSample: message.bits_per_sample,
+                        pcmFormat: message.pcm_format,
                     };
                     if (
-                        !isNaN(audioInfo.channel) &&
-                        !isNaN(audioInfo.sampleRate) &&
-                        !isNaN(audioInfo.bitsPerSample) &&
+                        Number.isInteger(audioInfo.channel) &&
+                        Number.isInteger(audioInfo.sampleRate) &&
+                        Number.isInteger(audioInfo.bitsPerSample) &&
                         audioInfo.pcmFormat
                     ) {
                         //* step3: set AudioInfo state *//
@@ -162,12 +402,112 @@ const useWebSocket = (url: string): UseWebSocketHook => {
                             sampleRate: audioInfo.sampleRate,
                         });
                         //* step5: send accept message *//
-                        ws.current?.send('accept');
+                        send({ version: PROTOCOL_VERSION, type: 'accept' });
                     } else {
-                        console.error(`Invalid AudioInfo format: ${event.data}`);
+                        console.error('Invalid AudioInfo format:', message);
                         setError('invalid_audio_info');
                     }
+                    break;
                 }
+                case 'audio_chunk':
+                    //* step8: play PCM data *//
+                    if (audioContext.current && audioInfoRef.current && message.pcm.length > 0) {
+                        // the chunk starts playing now
+                        streamClock.current = {
+                            time: message.start_frame / audioInfoRef.current.sampleRate,
+                            at: performance.now(),
+                        };
+                        try {
+                            // decodeAudioDataの代わりに、新しいヘルパー関数を呼び出す
+                            playRawPCM(audioContext.current, message.pcm, audioInfoRef.current);
+                        } catch (err) {
+                            console.error('Failed to play raw PCM data:', err);
+                            setError('play');
+                        }
+                    }
+                    break;
+                case 'analysis':
+                    //* step7: set BPM and beat grid *//
+                    setBpmState(message.bpm);
+                    setConfidenceState(message.confidence);
+                    bpmRef.current = message.bpm ?? 0;
+                    setMeterState(message.meter);
+                    if (message.meter) {
+                        beatsPerBarRef.current = BEATS_PER_BAR[message.meter];
+                    }
+                    if (message.tempo_curve.length > 0) {
+                        setTempoCurveState((curve) => [...curve, ...message.tempo_curve]);
+                    }
+                    setKeyState(message.key);
+                    setHpssState(message.hpss);
+                    setStereoState(message.stereo);
+                    if (message.grooves.length > 0) {
+                        setGrooveState(message.grooves[message.grooves.length - 1]);
+                    }
+                    for (const chord of message.chords) {
+                        // a chord with the same start continues the last reported chord
+                        const last = chords.current[chords.current.length - 1];
+                        if (last != null && last.start_frame === chord.start_frame) {
+                            chords.current[chords.current.length - 1] = chord;
+                        } else {
+                            chords.current.push(chord);
+                        }
+                    }
+                    chords.current = chords.current.slice(-CHORD_HISTORY);
+                    if (message.beats.length > 0) {
+                        lastBeat.current = message.beats[message.beats.length - 1];
+                    }
+                    break;
+                case 'loudness':
+                    //* step10: set the meters *//
+                    setLoudnessState(message);
+                    if (message.clipping) {
+                        console.warn('Clipping at frame', message.start_frame);
+                    }
+                    break;
+                case 'spectrum':
+                    //* step11: set the band levels *//
+                    setSpectrumState(message.bands);
+                    break;
+                case 'onset':
+                    //* step12: queue the hit until it is played *//
+                    pendingOnsets.current.push(message);
+                    break;
+                case 'section_change':
+                    //* step13: set the section *//
+                    setSectionState(message);
+                    break;
+                case 'pitch': {
+                    //* step14: add the pitch frames to the melody line *//
+                    const hop = message.hop_frames / (audioInfoRef.current?.sampleRate ?? 1);
+                    const points = message.f0.map((f0, index) => ({
+                        time: message.start_time + index * hop,
+                        f0,
+                        voicing: message.voicing[index],
+                    }));
+                    const end = points[points.length - 1]?.time ?? 0;
+                    setMelodyState((melody) =>
+                        [...melody, ...points].filter((point) => point.time > end - MELODY_LENGTH)
+                    );
+                    break;
+                }
+                case 'summary':
+                    //* step9: set the analysis of the whole track *//
+                    setTrackKeyState(message.key);
+                    setMeterState(message.meter);
+                    setTrackSectionsState(message.sections);
+                    setTempoCurveState(message.tempo_curve);
+                    setTrackGrooveState(message.groove);
+                    break;
+                case 'error':
+                    console.error('Server reported an error:', message.reason);
+                    setError('protocol');
+                    break;
+                case 'end_of_stream':
+                    console.log('End of stream');
+                    break;
+                default:
+                    console.log('Received unknown MessagePack data:', message);
             }
         };
 
@@ -181,7 +521,7 @@ const useWebSocket = (url: string): UseWebSocketHook => {
             setError('websocket');
             setReadyState('disconnected');
         };
-    }, []);
+    }, [trackId, tempoDetector, hpss, channelMode]);
 
     // connect handler
     const connect = useCallback(() => {
@@ -208,6 +548,57 @@ const useWebSocket = (url: string): UseWebSocketHook => {
         audioContext.current?.close();
     }, []);
 
+    // pulse on the beats predicted from the latest beat grid
+    // (the analysis lags behind the playback, so the beats are extrapolated)
+    useEffect(() => {
+        let frame = 0;
+        const tick = () => {
+            frame = requestAnimationFrame(tick);
+            const clock = streamClock.current;
+            if (clock == null) {
+                return;
+            }
+            const now = clock.time + (performance.now() - clock.at) / 1000;
+
+            // light the lamp of each hit while it is being played
+            const hits: Record<Instrument, number> = { kick: 0, snare: 0, hi_hat: 0 };
+            pendingOnsets.current = pendingOnsets.current.filter((onset) => onset.time + ONSET_FLASH_LENGTH > now);
+            for (const onset of pendingOnsets.current) {
+                if (onset.time <= now) {
+                    hits[onset.instrument] = Math.max(hits[onset.instrument], onset.strength);
+                }
+            }
+            setHitState((state) =>
+                INSTRUMENTS.every((instrument) => state[instrument] === hits[instrument]) ? state : hits
+            );
+
+            // the chord that is being played
+            const chord = chords.current.find((chord) => chord.start_time <= now && now < chord.end_time) ?? null;
+            setChordState((state) =>
+                state?.start_frame === chord?.start_frame && state?.end_frame === chord?.end_frame ? state : chord
+            );
+
+            const beat = lastBeat.current;
+            if (beat == null || bpmRef.current <= 0) {
+                return;
+            }
+            const period = 60 / bpmRef.current;
+            const beatsSinceLast = Math.floor((now - beat.time) / period);
+            const phase = (now - beat.time) / period - beatsSinceLast;
+            // count on from the position of the latest beat in its bar
+            const beatInBar = (beat.beat_in_bar + beatsSinceLast) % beatsPerBarRef.current;
+            const pulse = phase < BEAT_PULSE_LENGTH;
+            const downbeat = pulse && beatInBar === 0;
+            setBeatState((state) =>
+                state.pulse === pulse && state.downbeat === downbeat && state.beatInBar === beatInBar
+                    ? state
+                    : { pulse, downbeat, beatInBar }
+            );
+        };
+        frame = requestAnimationFrame(tick);
+        return () => cancelAnimationFrame(frame);
+    }, []);
+
     // disconnect websocket when the component is unmounted.
     useEffect(() => {
         return () => {
@@ -216,15 +607,157 @@ const useWebSocket = (url: string): UseWebSocketHook => {
         };
     }, []);
 
-    return { audioInfoState, bpmState, readyState, error, connect, disconnect };
+    return {
+        audioInfoState,
+        bpmState,
+        confidenceState,
+        meterState,
+        tempoCurveState,
+        keyState,
+        chordState,
+        hpssState,
+        stereoState,
+        grooveState,
+        trackGrooveState,
+        trackKeyState,
+        sectionState,
+        trackSectionsState,
+        loudnessState,
+        spectrumState,
+        beatState,
+        hitState,
+        melodyState,
+        readyState,
+        error,
+        connect,
+        disconnect,
+    };
+};
+
+/* tempo curve chart, scaled to the range of the curve */
+const TempoCurveChart: FC<{ curve: TempoPoint[] }> = ({ curve }) => {
+    const width = 300;
+    const height = 60;
+    const start = curve[0].time;
+    const end = curve[curve.length - 1].time;
+    const minBpm = Math.min(...curve.map((point) => point.bpm)) - 1;
+    const maxBpm = Math.max(...curve.map((point) => point.bpm)) + 1;
+    const points = curve
+        .map(
+            (point) =>
+                `${((point.time - start) / Math.max(end - start, 1e-6)) * width},${
+                    height - ((point.bpm - minBpm) / (maxBpm - minBpm)) * height
+                }`
+        )
+        .join(' ');
+    return (
+        <div className="px-3 py-1 text-xs">
+            <svg width={width} height={height} className="bg-gray-50">
+                <polyline points={points} fill="none" stroke="rgb(99 102 241)" strokeWidth={1.5} />
+            </svg>
+            {minBpm.toFixed(0)} - {maxBpm.toFixed(0)} BPM
+        </div>
+    );
+};
+
+/* format a swing ratio with its note value (e.g. '1.60 (16ths)') */
+const formatSwing = (groove: TrackGroove) =>
+    groove.swing == null ? '-' : `${groove.swing.toFixed(2)} (${groove.subdivision}ths)`;
+
+/* micro-timing of each sixteenth note of a bar (up: late, down: early, up to 50 ms) */
+const GrooveChart: FC<{ deviations: (number | null)[] }> = ({ deviations }) => {
+    const width = 300;
+    const height = 40;
+    const slot = width / Math.max(deviations.length, 1);
+    const scale = height / 2 / 50;
+    return (
+        <div className="px-3 py-1">
+            <svg width={width} height={height} className="bg-gray-50">
+                <line x1={0} y1={height / 2} x2={width} y2={height / 2} stroke="rgb(209 213 219)" />
+                {deviations.map((deviation, index) => {
+                    if (deviation == null) {
+                        return null;
+                    }
+                    const length = Math.min(Math.abs(deviation) * scale, height / 2);
+                    return (
+                        <rect
+                            key={index}
+                            x={index * slot + 1}
+                            y={deviation > 0 ? height / 2 - length : height / 2}
+                            width={slot - 2}
+                            height={length}
+                            fill={index % 4 === 0 ? 'rgb(239 68 68)' : 'rgb(99 102 241)'}
+                        />
+                    );
+                })}
+            </svg>
+        </div>
+    );
+};
+
+/* melody chart of the last seconds, 55 Hz to 1760 Hz on a log scale */
+const MelodyChart: FC<{ melody: PitchPoint[] }> = ({ melody }) => {
+    const width = 300;
+    const height = 60;
+    const end = melody[melody.length - 1].time;
+    const y = (f0: number) => height - (Math.log2(f0 / 55) / 5) * height;
+    return (
+        <div className="px-3 py-1">
+            <svg width={width} height={height} className="bg-gray-50">
+                {melody
+                    .filter((point) => point.voicing >= MIN_VOICING && point.f0 > 0)
+                    .map((point) => (
+                        <circle
+                            key={point.time}
+                            cx={((point.time - end + MELODY_LENGTH) / MELODY_LENGTH) * width}
+                            cy={y(point.f0)}
+                            r={1.5}
+                            fill="rgb(16 185 129)"
+                        />
+                    ))}
+            </svg>
+        </div>
+    );
 };
 
 const App: FC = () => {
     // server URL state
     const [serverUrl, setServerUrl] = useState<string>('ws://localhost:7000');
+    // track ID state (empty: the server picks its default track)
+    const [trackId, setTrackId] = useState<string>('');
+    // tempo detector state (empty: the middle-server picks its default detector)
+    const [tempoDetector, setTempoDetector] = useState<TempoDetectorKind | ''>('');
+    // harmonic/percussive separation state (null: the middle-server default)
+    const [hpss, setHpss] = useState<boolean | null>(null);
+    // channel mode state (empty: the mono downmix)
+    const [channelMode, setChannelMode] = useState<ChannelMode | ''>('');
 
     // useWebSocket hook
-    const { audioInfoState, bpmState, readyState, error, connect, disconnect } = useWebSocket(serverUrl);
+    const {
+        audioInfoState,
+        bpmState,
+        confidenceState,
+        meterState,
+        tempoCurveState,
+        keyState,
+        chordState,
+        hpssState,
+        stereoState,
+        grooveState,
+        trackGrooveState,
+        trackKeyState,
+        sectionState,
+        trackSectionsState,
+        loudnessState,
+        spectrumState,
+        beatState,
+        hitState,
+        melodyState,
+        readyState,
+        error,
+        connect,
+        disconnect,
+    } = useWebSocket(serverUrl, trackId.trim(), tempoDetector, hpss, channelMode);
 
     // connect handler
     const handleConnect = () => {
@@ -241,6 +774,26 @@ const App: FC = () => {
         setServerUrl(e.target.value);
     };
 
+    // set track ID handler
+    const handleTrackIdChange = (e: React.ChangeEvent<HTMLInputElement>) => {
+        setTrackId(e.target.value);
+    };
+
+    // set tempo detector handler
+    const handleTempoDetectorChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+        setTempoDetector(e.target.value as TempoDetectorKind | '');
+    };
+
+    // set harmonic/percussive separation handler
+    const handleHpssChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+        setHpss(e.target.value === '' ? null : e.target.value === 'on');
+    };
+
+    // set channel mode handler
+    const handleChannelModeChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+        setChannelMode(e.target.value as ChannelMode | '');
+    };
+
     return (
         <main className="p-4 md:p-6 lg:p-8 grid grid-cols-1 md:grid-cols-3 gap-6">
             {/* server url setting form */}
@@ -257,6 +810,67 @@ const App: FC = () => {
                     disabled={readyState === 'connected' || readyState === 'connecting'}
                 />
             </div>
+            {/* track setting form */}
+            <div>
+                <label htmlFor="trackId" className="block text-sm font-medium text-gray-600 mb-1">
+                    Track ID
+                </label>
+                <input
+                    type="text"
+                    id="trackId"
+                    value={trackId}
+                    placeholder="default"
+                    onChange={handleTrackIdChange}
+                    className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-indigo-500 transition"
+                    disabled={readyState === 'connected' || readyState === 'connecting'}
+                />
+                <label htmlFor="tempoDetector" className="block text-sm font-medium text-gray-600 mt-2 mb-1">
+                    Tempo Detector
+                </label>
+                <select
+                    id="tempoDetector"
+                    value={tempoDetector}
+                    onChange={handleTempoDetectorChange}
+                    className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-indigo-500 transition"
+                    disabled={readyState === 'connected' || readyState === 'connecting'}
+                >
+                    <option value="">default</option>
+                    <option value="librosa">librosa</option>
+                    <option value="native">native</option>
+                    <option value="python">python</option>
+                </select>
+                <label htmlFor="hpss" className="block text-sm font-medium text-gray-600 mt-2 mb-1">
+                    Harmonic/Percussive Separation
+                </label>
+                <select
+                    id="hpss"
+                    value={hpss == null ? '' : hpss ? 'on' : 'off'}
+                    onChange={handleHpssChange}
+                    className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-indigo-500 transition"
+                    disabled={readyState === 'connected' || readyState === 'connecting'}
+                >
+                    <option value="">default</option>
+                    <option value="on">on</option>
+                    <option value="off">off</option>
+                </select>
+                <label htmlFor="channelMode" className="block text-sm font-medium text-gray-600 mt-2 mb-1">
+                    Channel Mode
+                </label>
+                <select
+                    id="channelMode"
+                    value={channelMode}
+                    onChange={handleChannelModeChange}
+                    className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-indigo-500 transition"
+                    disabled={readyState === 'connected' || readyState === 'connecting'}
+                >
+                    <option value="">default</option>
+                    <option value="mono">mono</option>
+                    <option value="left">left</option>
+                    <option value="right">right</option>
+                    <option value="mid">mid</option>
+                    <option value="side">side</option>
+                </select>
+            </div>
             {/* connection control panel */}
             <div className="items-center justify-between">
                 <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>{readyState}</div>
@@ -266,7 +880,109 @@ const App: FC = () => {
                         : 'No AudioInfo'}
                 </div>
                 <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
-                    BPM: {bpmState > 0 ? bpmState : 'Not Set'}
+                    BPM:{' '}
+                    {bpmState != null
+                        ? `${bpmState.toFixed(1)} (confidence ${confidenceState.toFixed(2)})`
+                        : 'No Tempo'}
+                    {meterState && ` / Meter: ${meterState}`}
+                </div>
+                {/* tempo curve (BPM over the stream time) */}
+                {tempoCurveState.length > 1 && <TempoCurveChart curve={tempoCurveState} />}
+                <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                    Key: {keyState ? `${keyState.name} (${keyState.camelot})` : 'Not Set'}
+                    {trackKeyState && ` / Track Key: ${trackKeyState.name} (${trackKeyState.camelot})`}
+                </div>
+                <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                    Chord: {chordState ? (chordState.name ?? 'N.C.') : 'Not Set'}
+                </div>
+                {hpssState && (
+                    <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                        Harmonic: {formatLevel(hpssState.harmonic_level)} dBFS, Percussive:{' '}
+                        {formatLevel(hpssState.percussive_level)} dBFS (
+                        {(hpssState.percussive_ratio * 100).toFixed(0)}% percussive)
+                    </div>
+                )}
+                {/* groove of the latest bar, and of the whole track once the stream ends */}
+                {grooveState && (
+                    <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                        Swing: {formatSwing(grooveState)} in bar {grooveState.bar}
+                        {trackGrooveState && ` / Track Swing: ${formatSwing(trackGrooveState)}`}
+                    </div>
+                )}
+                {(trackGrooveState ?? grooveState) && (
+                    <GrooveChart deviations={(trackGrooveState ?? grooveState)?.deviations ?? []} />
+                )}
+                {/* stereo image (a negative correlation cancels out in mono) */}
+                {stereoState && (
+                    <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                        <span>Channels: {stereoState.levels.map(formatLevel).join(' / ')} dBFS</span>
+                        {stereoState.correlation != null && (
+                            <span>, Correlation: {stereoState.correlation.toFixed(2)}</span>
+                        )}
+                        {stereoState.width != null && <span>, Width: {stereoState.width.toFixed(2)}</span>}
+                        {stereoState.correlation != null && stereoState.correlation < 0 && (
+                            <span className="ml-2 text-red-600">PHASE</span>
+                        )}
+                    </div>
+                )}
+                <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                    Section:{' '}
+                    {sectionState
+                        ? `${sectionState.label} from ${sectionState.start_time.toFixed(1)}s (confidence ${sectionState.confidence.toFixed(2)})`
+                        : 'No Section'}
+                </div>
+                {/* structure of the whole track */}
+                {trackSectionsState.length > 0 && (
+                    <ol className="px-3 py-1 text-xs">
+                        {trackSectionsState.map((section) => (
+                            <li key={section.start_frame}>
+                                {section.start_time.toFixed(1)}s - {section.end_time?.toFixed(1)}s: {section.label}
+                            </li>
+                        ))}
+                    </ol>
+                )}
+                {/* VU meters */}
+                <div className={`px-3 py-1 text-sm font-semibold rounded-full`}>
+                    {loudnessState ? (
+                        <>
+                            <span>M: {formatLevel(loudnessState.momentary)} LUFS, </span>
+                            <span>S: {formatLevel(loudnessState.short_term)} LUFS, </span>
+                            <span>RMS: {loudnessState.rms.map(formatLevel).join(' / ')} dBFS, </span>
+                            <span>True Peak: {loudnessState.true_peak.map(formatLevel).join(' / ')} dBTP</span>
+                        </>
+                    ) : (
+                        'No Loudness'
+                    )}
+                    {loudnessState?.clipping && <span className="ml-2 text-red-600">CLIP</span>}
+                </div>
+                {/* spectrum (log-spaced bands, bass on the left) */}
+                <div className="mx-3 my-1 flex items-end h-16 gap-px">
+                    {Array.from(spectrumState ?? []).map((level, band) => (
+                        <div key={band} className="flex-1 bg-indigo-400" style={{ height: `${(level / 255) * 100}%` }} />
+                    ))}
+                </div>
+                {/* beat pulse (red on the downbeat) and the beat in the bar */}
+                <div className="mx-3 my-1 flex items-center gap-2">
+                    <div
+                        className={`w-6 h-6 rounded-full transition-colors ${
+                            beatState.downbeat ? 'bg-red-500' : beatState.pulse ? 'bg-indigo-500' : 'bg-gray-200'
+                        }`}
+                    />
+                    <span className="text-sm font-semibold">{beatState.beatInBar + 1}</span>
+                </div>
+                {/* melody line (voiced frames, log frequency) */}
+                {melodyState.length > 0 && <MelodyChart melody={melodyState} />}
+                {/* hit lamps (kick, snare, hi-hat), brighter on stronger hits */}
+                <div className="mx-3 my-1 flex gap-2">
+                    {INSTRUMENTS.map((instrument) => (
+                        <div
+                            key={instrument}
+                            className="px-2 py-1 text-xs font-semibold rounded bg-amber-400"
+                            style={{ opacity: 0.15 + 0.85 * hitState[instrument] }}
+                        >
+                            {instrument}
+                        </div>
+                    ))}
                 </div>
                 <div className="flex space-x-2">
                     <button
@@ -0,0 +1,101 @@
+use crate::errors::protocol::ProtocolError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The encoding of the samples in a PCM chunk.
+///
+/// Every format is interleaved and little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleFormat {
+    /// Signed 16-bit integer.
+    S16le,
+    /// Signed 24-bit integer packed into 3 bytes.
+    S24le,
+    /// Signed 32-bit integer.
+    S32le,
+    /// 32-bit IEEE float in the range [-1.0, 1.0].
+    F32le,
+}
+
+impl SampleFormat {
+    pub fn bits_per_sample(self) -> u16 {
+        match self {
+            SampleFormat::S16le => 16,
+            SampleFormat::S24le => 24,
+            SampleFormat::S32le | SampleFormat::F32le => 32,
+        }
+    }
+
+    pub fn bytes_per_sample(self) -> usize {
+        self.bits_per_sample() as usize / 8
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SampleFormat::S16le => "s16le",
+            SampleFormat::S24le => "s24le",
+            SampleFormat::S32le => "s32le",
+            SampleFormat::F32le => "f32le",
+        }
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioInfo {
+    /// The number of channels.
+    pub channels: u16,
+
+    /// The number of samples per second.
+    ///
+    /// A common value is 44100, this is 44.1 kHz which is used for CD audio.
+    pub sample_rate: u32,
+
+    /// The number of bits per sample.
+    ///
+    /// A common value is 16 bits per sample, which is used for CD audio.
+    pub bits_per_sample: u16,
+
+    /// The encoding of the samples.
+    pub pcm_format: SampleFormat,
+}
+
+impl AudioInfo {
+    /// Describe a PCM stream independently of the container or codec it was decoded from.
+    pub fn new(channels: u16, sample_rate: u32, pcm_format: SampleFormat) -> Self {
+        AudioInfo {
+            channels,
+            sample_rate,
+            bits_per_sample: pcm_format.bits_per_sample(),
+            pcm_format,
+        }
+    }
+
+    /// The size of one frame (one sample of every channel) in bytes.
+    pub fn bytes_per_frame(&self) -> usize {
+        self.channels as usize * self.pcm_format.bytes_per_sample()
+    }
+
+    /// Check that the stream is non-empty and the sample size agrees with the format.
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        if self.channels == 0 || self.sample_rate == 0 {
+            return Err(ProtocolError::ParseAudioInfoError(format!(
+                "{} channels at {} Hz is not a valid stream",
+                self.channels, self.sample_rate
+            )));
+        }
+        if self.bits_per_sample != self.pcm_format.bits_per_sample() {
+            return Err(ProtocolError::ParseAudioInfoError(format!(
+                "{} bits per sample does not match {}",
+                self.bits_per_sample, self.pcm_format
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// WebSocket close codes (RFC 6455, section 7.4.1).
+pub mod close_code {
+    /// The session ended normally.
+    pub const NORMAL: u16 = 1000;
+    /// The peer sent data this endpoint cannot accept.
+    pub const UNSUPPORTED_DATA: u16 = 1003;
+    /// The peer sent a message that violates the protocol.
+    pub const POLICY_VIOLATION: u16 = 1008;
+    /// This endpoint failed to fulfil the request.
+    pub const INTERNAL_ERROR: u16 = 1011;
+}
+
+/// The longest close reason a control frame can carry, in bytes.
+const MAX_CLOSE_REASON_LENGTH: usize = 123;
+
+#[derive(Debug)]
+pub struct AppError {
+    pub status_code: StatusCode,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseError {
+    pub message: String,
+}
+
+impl AppError {
+    /// The close code that ends a WebSocket session failing with this error.
+    pub fn close_code(&self) -> u16 {
+        match self.status_code {
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => close_code::UNSUPPORTED_DATA,
+            status_code if status_code.is_client_error() => close_code::POLICY_VIOLATION,
+            _ => close_code::INTERNAL_ERROR,
+        }
+    }
+
+    /// The message, cut at a char boundary to fit in a close frame.
+    pub fn close_reason(&self) -> String {
+        let mut end = self.message.len().min(MAX_CLOSE_REASON_LENGTH);
+        while !self.message.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.message[..end].to_string()
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (
+            self.status_code,
+            Json(json!(ResponseError {
+                message: self.message,
+            })),
+        )
+            .into_response()
+    }
+}
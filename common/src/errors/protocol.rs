@@ -0,0 +1,45 @@
+use super::app::AppError;
+use axum::http::StatusCode;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("UnexpectedMessageTypeError: unsupported message type received")]
+    UnexpectedMessageTypeError,
+    #[error("UnexpectedMessageError: {0}")]
+    UnexpectedMessageError(String),
+    #[error("UnsupportedProtocolVersionError: protocol version {0} is not supported")]
+    UnsupportedProtocolVersionError(u16),
+    #[error("ParseAudioInfoError: Invalid audio info format: {0}")]
+    ParseAudioInfoError(String),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+}
+
+impl From<ProtocolError> for AppError {
+    fn from(error: ProtocolError) -> Self {
+        match error {
+            ProtocolError::UnexpectedMessageTypeError => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: "UnexpectedMessageTypeError: unsupported message type received".into(),
+            },
+            ProtocolError::UnexpectedMessageError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("UnexpectedMessageError: {e}"),
+            },
+            ProtocolError::UnsupportedProtocolVersionError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!(
+                    "UnsupportedProtocolVersionError: protocol version {e} is not supported"
+                ),
+            },
+            ProtocolError::ParseAudioInfoError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("ParseAudioInfoError: Invalid audio info format: {e}"),
+            },
+            ProtocolError::SerdeJsonError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("SerdeJsonError: {e}"),
+            },
+        }
+    }
+}
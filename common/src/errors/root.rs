@@ -4,4 +4,6 @@ pub enum RootError {
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error("ConfigError: {0}")]
+    ConfigError(String),
 }
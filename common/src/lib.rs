@@ -0,0 +1,5 @@
+//! Wire types shared by the server and the middle-server.
+
+pub mod audio;
+pub mod errors;
+pub mod protocol;
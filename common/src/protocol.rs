@@ -0,0 +1,164 @@
+use crate::{audio::AudioInfo, errors::protocol::ProtocolError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The newest control protocol version this binary speaks.
+pub const PROTOCOL_VERSION: u16 = 1;
+/// The oldest control protocol version this binary still accepts.
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// A control message, sent as a JSON text frame.
+///
+/// e.g. `{"version":1,"type":"open","track_id":"sample3"}`
+///
+/// Unknown fields are ignored, so new optional fields can be added without bumping the version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlFrame {
+    /// The protocol version of the sender.
+    pub version: u16,
+
+    #[serde(flatten)]
+    pub message: ControlMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    /// Greeting that announces the protocol version of the sender.
+    Hello,
+    /// Request to open a track. The default track is used if `track_id` is omitted.
+    ///
+    /// `analysis` is read by the middle-server; the server ignores it.
+    Open {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        track_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        analysis: Option<AnalysisOptions>,
+    },
+    /// Acceptance of the stream header. The server starts sending PCM data.
+    Accept,
+    /// The format of the PCM data that follows.
+    StreamHeader(AudioInfo),
+    /// The sender rejected a message or failed.
+    Error { reason: String },
+    /// The sender ends the session, e.g. at the end of the stream.
+    Bye {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+/// Per-session analysis options. Unset options fall back to the middle-server configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tempo_detector: Option<TempoDetectorKind>,
+    /// The number of spectrum bands, 0 to turn the spectrum off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spectrum_bands: Option<u16>,
+    /// The number of spectrum updates per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spectrum_rate: Option<f64>,
+    /// Split each analysis window into its harmonic and percussive parts, and estimate the tempo
+    /// and the onsets from the percussive part.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hpss: Option<bool>,
+    /// The signal that is analyzed, the mono downmix if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_mode: Option<ChannelMode>,
+}
+
+/// The signal of a multichannel stream that is analyzed.
+///
+/// `left` and `right` are the first two channels; `mid` and `side` are their sum and difference.
+/// A mono stream is analyzed as it is in every mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelMode {
+    /// The average of every channel.
+    #[default]
+    Mono,
+    Left,
+    Right,
+    /// `(left + right) / 2`, what the left and right channels have in common.
+    Mid,
+    /// `(left - right) / 2`, what differs between the left and right channels.
+    Side,
+}
+
+impl ChannelMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelMode::Mono => "mono",
+            ChannelMode::Left => "left",
+            ChannelMode::Right => "right",
+            ChannelMode::Mid => "mid",
+            ChannelMode::Side => "side",
+        }
+    }
+}
+
+impl fmt::Display for ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The backends that estimate the tempo of an analysis window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TempoDetectorKind {
+    /// `librosa.beat.beat_track` through Python.
+    Librosa,
+    /// The pure-Rust estimator of the middle-server.
+    Native,
+    /// The Python callable configured on the middle-server.
+    Python,
+}
+
+impl TempoDetectorKind {
+    pub const ALL: [TempoDetectorKind; 3] = [
+        TempoDetectorKind::Librosa,
+        TempoDetectorKind::Native,
+        TempoDetectorKind::Python,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TempoDetectorKind::Librosa => "librosa",
+            TempoDetectorKind::Native => "native",
+            TempoDetectorKind::Python => "python",
+        }
+    }
+}
+
+impl fmt::Display for TempoDetectorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ControlFrame {
+    /// Wrap a message in a frame of the current protocol version.
+    pub fn new(message: ControlMessage) -> Self {
+        ControlFrame {
+            version: PROTOCOL_VERSION,
+            message,
+        }
+    }
+
+    /// Parse a frame, rejecting unknown messages and unsupported versions.
+    pub fn from_json(text: &str) -> Result<Self, ProtocolError> {
+        let frame: ControlFrame = serde_json::from_str(text)?;
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&frame.version) {
+            return Err(ProtocolError::UnsupportedProtocolVersionError(
+                frame.version,
+            ));
+        }
+        Ok(frame)
+    }
+
+    pub fn to_json(&self) -> Result<String, ProtocolError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
@@ -0,0 +1,18 @@
+pub mod beat;
+pub mod channel;
+pub mod chord;
+pub mod chroma;
+pub mod groove;
+pub mod hpss;
+pub mod key;
+pub mod level;
+pub mod loudness;
+pub mod meter;
+pub mod onset;
+pub mod pitch;
+pub mod segment;
+pub mod spectrum;
+pub mod stft;
+pub mod tempo;
+pub mod tempo_curve;
+pub mod tracker;
@@ -0,0 +1,160 @@
+use crate::analysis::stft::{HOP_LENGTH, N_FFT, Stft};
+
+/// How strictly the beat tracker keeps to the tempo, like `tightness` of `librosa.beat.beat_track`.
+const TIGHTNESS: f32 = 100.0;
+/// The upper edge of the band whose energy marks the downbeats (kick drum and bass).
+const LOW_FREQUENCY_CUTOFF: f32 = 150.0;
+
+/// Track the beats of an onset strength envelope at `bpm` (dynamic programming, Ellis 2007).
+///
+/// Returns the indices of the envelope frames that are on a beat, in ascending order.
+pub fn track_beats(envelope: &[f32], frame_rate: f32, bpm: f32) -> Vec<usize> {
+    if envelope.is_empty() || bpm <= 0.0 {
+        return Vec::new();
+    }
+    let period = 60.0 * frame_rate / bpm;
+    if period < 1.0 {
+        return Vec::new();
+    }
+
+    // normalize the envelope and smooth it with a Gaussian around the beat period
+    let std = standard_deviation(envelope);
+    if std <= f32::EPSILON {
+        return Vec::new();
+    }
+    let radius = period.round() as isize;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|offset| {
+            let x = offset as f32 * 32.0 / period;
+            (-0.5 * x * x).exp()
+        })
+        .collect();
+    let local_score: Vec<f32> = (0..envelope.len() as isize)
+        .map(|t| {
+            kernel
+                .iter()
+                .enumerate()
+                .filter_map(|(k, weight)| {
+                    let index = t + k as isize - radius;
+                    (index >= 0 && (index as usize) < envelope.len())
+                        .then(|| weight * envelope[index as usize] / std)
+                })
+                .sum()
+        })
+        .collect();
+
+    // the best score of a beat sequence that ends at each frame
+    let mut cumulative_score = vec![0.0f32; envelope.len()];
+    let mut backlink: Vec<Option<usize>> = vec![None; envelope.len()];
+    let min_gap = (period / 2.0).round().max(1.0) as usize;
+    let max_gap = (2.0 * period).round() as usize;
+    for t in 0..envelope.len() {
+        let mut best: Option<(usize, f32)> = None;
+        if t >= min_gap {
+            let candidates = cumulative_score
+                .iter()
+                .enumerate()
+                .take(t - min_gap + 1)
+                .skip(t.saturating_sub(max_gap));
+            for (previous, previous_score) in candidates {
+                let deviation = ((t - previous) as f32 / period).ln();
+                let score = previous_score - TIGHTNESS * deviation * deviation;
+                if best.is_none_or(|(_, best_score)| score > best_score) {
+                    best = Some((previous, score));
+                }
+            }
+        }
+        cumulative_score[t] = local_score[t] + best.map_or(0.0, |(_, score)| score.max(0.0));
+        backlink[t] = best
+            .filter(|(_, score)| *score > 0.0)
+            .map(|(previous, _)| previous);
+    }
+
+    // the last beat is the last local maximum that is strong enough
+    let maxima: Vec<usize> = (1..cumulative_score.len().saturating_sub(1))
+        .filter(|&t| {
+            cumulative_score[t] > cumulative_score[t - 1]
+                && cumulative_score[t] >= cumulative_score[t + 1]
+        })
+        .collect();
+    let Some(median) = median(maxima.iter().map(|&t| cumulative_score[t]).collect()) else {
+        return Vec::new();
+    };
+    let Some(&last) = maxima
+        .iter()
+        .rev()
+        .find(|&&t| cumulative_score[t] >= 0.5 * median)
+    else {
+        return Vec::new();
+    };
+
+    let mut beats = vec![last];
+    while let Some(previous) = backlink[*beats.last().unwrap_or(&0)] {
+        beats.push(previous);
+    }
+    beats.reverse();
+
+    // drop the weak beats at the edges (e.g. the silence before the music starts)
+    let threshold = 0.5 * root_mean_square(beats.iter().map(|&t| local_score[t]));
+    let first = beats.iter().position(|&t| local_score[t] >= threshold);
+    let last = beats.iter().rposition(|&t| local_score[t] >= threshold);
+    match (first, last) {
+        (Some(first), Some(last)) => beats[first..=last].to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// The energy below `LOW_FREQUENCY_CUTOFF` at each beat (in seconds from the start of `samples`).
+pub fn low_frequency_energy(samples: &[f32], sample_rate: u32, beats: &[f64]) -> Vec<f32> {
+    let stft = Stft::new(samples, sample_rate, N_FFT, HOP_LENGTH);
+    let cutoff = (1..=N_FFT / 2)
+        .take_while(|&bin| stft.bin_frequency(bin) <= LOW_FREQUENCY_CUTOFF)
+        .last()
+        .unwrap_or(1);
+    let frame_rate = stft.frame_rate() as f64;
+    beats
+        .iter()
+        .map(|beat| {
+            let frame = ((beat * frame_rate).round() as usize).min(stft.frames.len() - 1);
+            // the attack of a kick can land a frame after the tracked beat
+            stft.frames[frame..(frame + 2).min(stft.frames.len())]
+                .iter()
+                .map(|bins| {
+                    bins[1..=cutoff]
+                        .iter()
+                        .map(|bin| bin.norm_sqr())
+                        .sum::<f32>()
+                })
+                .fold(0.0, f32::max)
+        })
+        .collect()
+}
+
+fn standard_deviation(values: &[f32]) -> f32 {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    (values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / values.len() as f32)
+        .sqrt()
+}
+
+fn root_mean_square(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| {
+        (sum + value * value, count + 1)
+    });
+    if count == 0 {
+        0.0
+    } else {
+        (sum / count as f32).sqrt()
+    }
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    Some(values[values.len() / 2])
+}
@@ -0,0 +1,84 @@
+use crate::analysis::level::is_silent;
+use common::protocol::ChannelMode;
+
+/// The stereo image of the first two channels of a signal.
+pub struct StereoImage {
+    /// The correlation of the left and right channels, from -1.0 (out of phase) through 0.0
+    /// (unrelated) to 1.0 (mono).
+    pub correlation: f32,
+    /// The share of the side signal in the energy, from 0.0 (mono) through 0.5 (unrelated
+    /// channels) to 1.0 (out of phase).
+    pub width: f32,
+}
+
+/// Split interleaved samples into one signal per channel.
+pub fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    if channels == 0 {
+        return Vec::new();
+    }
+    let mut signals = vec![Vec::with_capacity(samples.len() / channels); channels];
+    for frame in samples.chunks_exact(channels) {
+        for (signal, sample) in signals.iter_mut().zip(frame) {
+            signal.push(*sample);
+        }
+    }
+    signals
+}
+
+/// The signal of `mode` (see [`ChannelMode`]) from deinterleaved `signals`.
+pub fn select(signals: &[Vec<f32>], mode: ChannelMode) -> Vec<f32> {
+    match (signals, mode) {
+        ([], _) => Vec::new(),
+        // a mono signal has nothing to select
+        ([signal], _) => signal.clone(),
+        ([left, ..], ChannelMode::Left) => left.clone(),
+        ([_, right, ..], ChannelMode::Right) => right.clone(),
+        ([left, right, ..], ChannelMode::Mid) => {
+            left.iter().zip(right).map(|(l, r)| (l + r) / 2.0).collect()
+        }
+        ([left, right, ..], ChannelMode::Side) => {
+            left.iter().zip(right).map(|(l, r)| (l - r) / 2.0).collect()
+        }
+        (_, ChannelMode::Mono) => {
+            let mut mix = vec![0.0; signals[0].len()];
+            for signal in signals {
+                for (mix, sample) in mix.iter_mut().zip(signal) {
+                    *mix += sample;
+                }
+            }
+            mix.iter_mut()
+                .for_each(|sample| *sample /= signals.len() as f32);
+            mix
+        }
+    }
+}
+
+/// The stereo image of the first two `signals` (`None` for a mono or silent signal).
+pub fn stereo_image(signals: &[Vec<f32>]) -> Option<StereoImage> {
+    let [left, right, ..] = signals else {
+        return None;
+    };
+    if is_silent(left) && is_silent(right) {
+        return None;
+    }
+    let (mut left_energy, mut right_energy, mut cross) = (0.0f64, 0.0f64, 0.0f64);
+    let (mut mid_energy, mut side_energy) = (0.0f64, 0.0f64);
+    for (l, r) in left.iter().zip(right) {
+        let (l, r) = (*l as f64, *r as f64);
+        left_energy += l * l;
+        right_energy += r * r;
+        cross += l * r;
+        mid_energy += (l + r) * (l + r);
+        side_energy += (l - r) * (l - r);
+    }
+    // a silent channel has nothing in common with the other one
+    let correlation = if left_energy > 0.0 && right_energy > 0.0 {
+        cross / (left_energy * right_energy).sqrt()
+    } else {
+        0.0
+    };
+    Some(StereoImage {
+        correlation: correlation.clamp(-1.0, 1.0) as f32,
+        width: (side_energy / (mid_energy + side_energy)) as f32,
+    })
+}
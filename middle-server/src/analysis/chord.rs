@@ -0,0 +1,322 @@
+use crate::analysis::{
+    chroma::{CHROMA_HOP_LENGTH, CHROMA_N_FFT, PITCH_CLASSES},
+    level::is_silent,
+};
+use std::{fmt, ops::Range};
+
+/// A frame has no chord if no triad matches its chroma better than this (uniform chroma: 0.5).
+const NO_CHORD_SCORE: f32 = 0.6;
+/// The score the smoothing gives up for a chord change, so a chord holds through passing notes.
+const CHANGE_PENALTY: f32 = 0.3;
+/// The states of the smoothing: 12 major triads, 12 minor triads and no chord.
+const STATES: usize = 25;
+const NO_CHORD: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    Major,
+    Minor,
+}
+
+/// A triad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    /// The pitch class of the root (0 = C).
+    pub root: usize,
+    pub quality: Quality,
+}
+
+impl Chord {
+    fn from_state(state: usize) -> Option<Self> {
+        match state {
+            0..12 => Some(Chord {
+                root: state,
+                quality: Quality::Major,
+            }),
+            12..24 => Some(Chord {
+                root: state - 12,
+                quality: Quality::Minor,
+            }),
+            _ => None,
+        }
+    }
+
+    // the pitch classes of the triad
+    fn pitch_classes(self) -> [usize; 3] {
+        let third = match self.quality {
+            Quality::Major => 4,
+            Quality::Minor => 3,
+        };
+        [self.root, (self.root + third) % 12, (self.root + 7) % 12]
+    }
+}
+
+impl fmt::Display for Chord {
+    /// e.g. `C`, `F#m`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self.quality {
+            Quality::Major => "",
+            Quality::Minor => "m",
+        };
+        write!(f, "{}{}", PITCH_CLASSES[self.root], suffix)
+    }
+}
+
+/// A run of frames with the same chord, covering the frames `start_frame..end_frame`
+/// of the stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChordSegment {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    /// `None` if there is no chord (e.g. silence, drums or noise).
+    pub chord: Option<Chord>,
+    /// How well the chroma matches the chord on average, from 0.0 to 1.0.
+    pub confidence: f32,
+}
+
+/// The chord of each frame of the chromagram of a mono signal (from `chromagram`) with its
+/// score, smoothed so that short deviations do not break a chord (Viterbi over the chords with
+/// a penalty for every change).
+pub fn recognize_chords(chromagram: &[[f32; 12]], samples: &[f32]) -> Vec<(Option<Chord>, f32)> {
+    // the score of each state in each frame
+    let scores: Vec<[f32; STATES]> = chromagram
+        .iter()
+        .enumerate()
+        .map(|(frame, chroma)| {
+            let mut scores = [0.0; STATES];
+            if is_silent_frame(samples, frame) {
+                scores[NO_CHORD] = 1.0;
+                return scores;
+            }
+            let norm = chroma.iter().map(|value| value * value).sum::<f32>().sqrt();
+            for (state, score) in scores[..NO_CHORD].iter_mut().enumerate() {
+                let chord = Chord::from_state(state).expect("a triad state");
+                // the cosine similarity with the triad template
+                let matched = chord
+                    .pitch_classes()
+                    .iter()
+                    .map(|&pitch_class| chroma[pitch_class])
+                    .sum::<f32>();
+                *score = if norm > f32::EPSILON {
+                    matched / (norm * 3.0f32.sqrt())
+                } else {
+                    0.0
+                };
+            }
+            scores[NO_CHORD] = NO_CHORD_SCORE;
+            scores
+        })
+        .collect();
+    if scores.is_empty() {
+        return Vec::new();
+    }
+
+    // Viterbi
+    let mut total = scores[0];
+    let mut backtrack: Vec<[usize; STATES]> = Vec::with_capacity(scores.len());
+    for frame_scores in &scores[1..] {
+        let (best_state, best_total) = best(&total);
+        let mut pointers = [0; STATES];
+        let mut next = [0.0; STATES];
+        for state in 0..STATES {
+            let (from, from_total) = if total[state] >= best_total - CHANGE_PENALTY {
+                (state, total[state])
+            } else {
+                (best_state, best_total - CHANGE_PENALTY)
+            };
+            pointers[state] = from;
+            next[state] = from_total + frame_scores[state];
+        }
+        backtrack.push(pointers);
+        total = next;
+    }
+    let mut state = best(&total).0;
+    let mut states = vec![state; scores.len()];
+    for (frame, pointers) in backtrack.iter().enumerate().rev() {
+        state = pointers[state];
+        states[frame] = state;
+    }
+
+    states
+        .into_iter()
+        .zip(scores)
+        .map(|(state, scores)| (Chord::from_state(state), scores[state]))
+        .collect()
+}
+
+/// The chords reported so far, so a chord that spans two windows is reported as one segment.
+#[derive(Debug, Default)]
+pub struct ChordTracker {
+    /// The last segment, which the next window may extend.
+    current: Option<ChordSegment>,
+    /// The number of frames of `current` (for its mean confidence).
+    current_frames: usize,
+}
+
+impl ChordTracker {
+    /// Map the chords of the window that starts at `start_frame` (from `recognize_chords`) onto
+    /// the stream timeline, and return the segments within `frames`.
+    ///
+    /// `frames` is the interior of the window (see `AnalysisJob::interior_end_frame`), where the
+    /// chords have context on both sides. The first segment continues the last segment of the
+    /// previous window if the chord is the same (with the same `start_frame`), and the last
+    /// segment may still be extended.
+    pub fn merge(
+        &mut self,
+        start_frame: u64,
+        frames: Range<u64>,
+        chords: &[(Option<Chord>, f32)],
+    ) -> Vec<ChordSegment> {
+        let hop = CHROMA_HOP_LENGTH as u64;
+        let mut segments: Vec<ChordSegment> = Vec::new();
+        for (index, &(chord, score)) in chords.iter().enumerate() {
+            // each frame covers half a hop on each side of its center
+            let center = start_frame + index as u64 * hop;
+            let frame_start = center.saturating_sub(hop / 2).max(frames.start);
+            let frame_end = (center + hop / 2).min(frames.end);
+            if frame_start >= frame_end {
+                continue;
+            }
+            let current = match self.current.as_mut() {
+                // the same chord goes on (a gap in the stream ends it)
+                Some(current) if current.chord == chord && frame_start <= current.end_frame => {
+                    current.end_frame = frame_end;
+                    current.confidence = (current.confidence * self.current_frames as f32 + score)
+                        / (self.current_frames + 1) as f32;
+                    self.current_frames += 1;
+                    *current
+                }
+                _ => {
+                    self.current_frames = 1;
+                    *self.current.insert(ChordSegment {
+                        start_frame: frame_start,
+                        end_frame: frame_end,
+                        chord,
+                        confidence: score,
+                    })
+                }
+            };
+            // report the latest state of each segment once
+            match segments.last_mut() {
+                Some(last) if last.start_frame == current.start_frame => *last = current,
+                _ => segments.push(current),
+            }
+        }
+        segments
+    }
+}
+
+// whether the frame centered on sample `frame * CHROMA_HOP_LENGTH` is silent
+fn is_silent_frame(samples: &[f32], frame: usize) -> bool {
+    let center = frame * CHROMA_HOP_LENGTH;
+    let start = center.saturating_sub(CHROMA_N_FFT / 2).min(samples.len());
+    let end = (center + CHROMA_N_FFT / 2).min(samples.len());
+    is_silent(&samples[start..end])
+}
+
+fn best(scores: &[f32; STATES]) -> (usize, f32) {
+    scores
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((NO_CHORD, 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMES_PER_CHORD: usize = 8;
+
+    fn chord(root: usize, quality: Quality) -> Option<Chord> {
+        Some(Chord { root, quality })
+    }
+
+    // a chroma frame with the pitch classes of `chord` and a little of every other one
+    fn chroma(chord: Chord) -> [f32; 12] {
+        let mut chroma = [0.1; 12];
+        for pitch_class in chord.pitch_classes() {
+            chroma[pitch_class] = 1.0;
+        }
+        chroma
+    }
+
+    // C - G - Am - F, each for `FRAMES_PER_CHORD` frames, with a loud enough signal
+    fn progression() -> (Vec<[f32; 12]>, Vec<f32>) {
+        let chords = [
+            chord(0, Quality::Major),
+            chord(7, Quality::Major),
+            chord(9, Quality::Minor),
+            chord(5, Quality::Major),
+        ];
+        let chromagram: Vec<[f32; 12]> = chords
+            .iter()
+            .flat_map(|chord| [chroma(chord.unwrap()); FRAMES_PER_CHORD])
+            .collect();
+        let samples = vec![0.5; chromagram.len() * CHROMA_HOP_LENGTH];
+        (chromagram, samples)
+    }
+
+    #[test]
+    fn passing_notes_do_not_break_a_chord() {
+        let (mut chromagram, samples) = progression();
+        // E minor for one frame in the middle of C major (B instead of C)
+        chromagram[3] = chroma(chord(4, Quality::Minor).unwrap());
+        let chords: Vec<Option<Chord>> = recognize_chords(&chromagram, &samples)
+            .into_iter()
+            .map(|(chord, _)| chord)
+            .collect();
+        let expected: Vec<Option<Chord>> = [
+            chord(0, Quality::Major),
+            chord(7, Quality::Major),
+            chord(9, Quality::Minor),
+            chord(5, Quality::Major),
+        ]
+        .iter()
+        .flat_map(|chord| [*chord; FRAMES_PER_CHORD])
+        .collect();
+        assert_eq!(chords, expected);
+    }
+
+    #[test]
+    fn silent_frames_have_no_chord() {
+        let (chromagram, mut samples) = progression();
+        samples[FRAMES_PER_CHORD * 3 * CHROMA_HOP_LENGTH..].fill(0.0);
+        let chords = recognize_chords(&chromagram, &samples);
+        assert_eq!(chords[0].0, chord(0, Quality::Major));
+        assert_eq!(chords.last().unwrap().0, None);
+    }
+
+    #[test]
+    fn chord_spanning_two_windows_is_one_segment() {
+        let (chromagram, samples) = progression();
+        let chords = recognize_chords(&chromagram, &samples);
+        let hop = CHROMA_HOP_LENGTH as u64;
+        let mut tracker = ChordTracker::default();
+        // the first window reports up to the middle of G major
+        let first = tracker.merge(0, 0..12 * hop, &chords);
+        assert_eq!(
+            first
+                .iter()
+                .map(|segment| segment.chord)
+                .collect::<Vec<_>>(),
+            vec![chord(0, Quality::Major), chord(7, Quality::Major)]
+        );
+        // the second window starts 4 frames later and goes on from there
+        let second = tracker.merge(4 * hop, 12 * hop..32 * hop, &chords[4..]);
+        assert_eq!(second[0].chord, chord(7, Quality::Major));
+        assert_eq!(second[0].start_frame, first[1].start_frame);
+        assert_eq!(
+            second
+                .iter()
+                .map(|segment| segment.chord)
+                .collect::<Vec<_>>(),
+            vec![
+                chord(7, Quality::Major),
+                chord(9, Quality::Minor),
+                chord(5, Quality::Major)
+            ]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use crate::analysis::stft::Stft;
+
+/// The FFT size of the chromagram; long enough to resolve semitones in the bass.
+pub const CHROMA_N_FFT: usize = 8192;
+/// The hop between two chroma frames.
+pub const CHROMA_HOP_LENGTH: usize = 2048;
+/// The lowest frequency that contributes to the chroma (C2).
+const MIN_FREQUENCY: f32 = 65.4;
+/// The highest frequency that contributes to the chroma (C7).
+const MAX_FREQUENCY: f32 = 2093.0;
+
+/// The pitch class names, starting at C.
+pub const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
+/// The pitch class (0 = C) of each STFT bin, `None` outside the chroma range.
+fn bin_pitch_classes(stft: &Stft) -> Vec<Option<usize>> {
+    (0..=stft.n_fft / 2)
+        .map(|bin| {
+            let frequency = stft.bin_frequency(bin);
+            if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+                return None;
+            }
+            let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+            Some((midi.round() as i64).rem_euclid(12) as usize)
+        })
+        .collect()
+}
+
+/// The chroma of each frame of a mono signal (`[frame][pitch class]`), each frame summing to 1
+/// (or to 0 for a silent frame).
+pub fn chromagram(samples: &[f32], sample_rate: u32) -> Vec<[f32; 12]> {
+    let stft = Stft::new(samples, sample_rate, CHROMA_N_FFT, CHROMA_HOP_LENGTH);
+    let pitch_classes = bin_pitch_classes(&stft);
+    stft.frames
+        .iter()
+        .map(|frame| {
+            let mut chroma = [0.0f32; 12];
+            for (bin, value) in frame.iter().enumerate() {
+                if let Some(pitch_class) = pitch_classes[bin] {
+                    chroma[pitch_class] += value.norm();
+                }
+            }
+            normalize(chroma)
+        })
+        .collect()
+}
+
+/// The mean chroma of a chromagram (from `chromagram`), summing to 1 (or to 0 for silence).
+pub fn mean_chroma(chromagram: &[[f32; 12]]) -> [f32; 12] {
+    let mut mean = [0.0f32; 12];
+    for chroma in chromagram {
+        for (sum, value) in mean.iter_mut().zip(chroma) {
+            *sum += value;
+        }
+    }
+    normalize(mean)
+}
+
+fn normalize(mut chroma: [f32; 12]) -> [f32; 12] {
+    let total: f32 = chroma.iter().sum();
+    if total > f32::EPSILON {
+        for value in chroma.iter_mut() {
+            *value /= total;
+        }
+    }
+    chroma
+}
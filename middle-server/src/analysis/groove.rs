@@ -0,0 +1,283 @@
+use serde::Serialize;
+
+/// The number of grid slots of a beat (sixteenth notes).
+const SLOTS_PER_BEAT: usize = 4;
+/// The off-beat of a pair of notes is looked for in this part of the pair, so the straight
+/// (0.5) and the triplet (0.67) off-beats are found but the next note is not.
+const OFF_BEAT_RANGE: (f64, f64) = (0.4, 0.8);
+/// An onset this close to a note on the grid (relative to the length of the pair) is that note.
+const GRID_TOLERANCE: f64 = 0.15;
+/// The sixteenth-note swing is reported if at least this many off-beats are found per beat.
+const MIN_SIXTEENTH_OFF_BEATS: f64 = 1.5;
+/// A beat interval longer than this many average intervals of the bar breaks the bar
+/// (e.g. the tempo got lost).
+const MAX_INTERVAL_RATIO: f64 = 2.0;
+/// Onsets this much older (in seconds) than the latest one are dropped, which is longer than a
+/// bar at any tempo plus an analysis window.
+const ONSET_HORIZON: f64 = 16.0;
+
+/// The groove of a bar.
+#[derive(Debug, Clone)]
+pub struct BarGroove {
+    pub bar: u64,
+    /// The times of the first beat of the bar and of the next bar in seconds from the start of
+    /// the stream.
+    pub start: f64,
+    pub end: f64,
+    /// The swing ratio of the bar: the length of the on-beat note over the length of the
+    /// off-beat note, 1.0 straight and 2.0 triplet swing (`None` without off-beats).
+    pub swing: Option<f32>,
+    /// The note value the swing is measured on: 8 (eighth notes) or 16 (sixteenth notes).
+    pub subdivision: u32,
+    /// How far the onset nearest each sixteenth-note slot of the bar is from the straight grid,
+    /// in milliseconds (positive is late, `None` without an onset).
+    pub deviations: Vec<Option<f32>>,
+}
+
+/// The groove of the whole track.
+#[derive(Debug, Serialize)]
+pub struct GrooveSummary {
+    /// The median swing ratio of the bars, `None` if no bar has off-beats.
+    pub swing: Option<f32>,
+    /// The note value most bars swing on, 8 or 16.
+    pub subdivision: u32,
+    /// The mean deviation of each slot in milliseconds, over the bars with the most common
+    /// number of slots.
+    pub deviations: Vec<Option<f32>>,
+}
+
+/// Measures the swing and the micro-timing of every bar from the beats and the onsets.
+///
+/// The onsets of a window are added before its beats, and a bar is measured once the downbeat
+/// of the next bar arrives.
+#[derive(Debug, Default)]
+pub struct GrooveTracker {
+    /// The onset times not older than the current bar.
+    onsets: Vec<f64>,
+    /// The bar that is being played, and the times of its beats.
+    bar: Option<(u64, Vec<f64>)>,
+    /// Every measured bar, for the summary.
+    bars: Vec<BarGroove>,
+}
+
+impl GrooveTracker {
+    /// Add onset times (in seconds from the start of the stream) that follow the previous
+    /// ones, in ascending order.
+    pub fn push_onsets(&mut self, times: impl IntoIterator<Item = f64>) {
+        self.onsets.extend(times);
+        if let Some(&last_onset) = self.onsets.last() {
+            self.onsets
+                .retain(|onset| *onset >= last_onset - ONSET_HORIZON);
+        }
+    }
+
+    /// Add the next beat (the `beat_in_bar`th beat of `bar`), and return the groove of the
+    /// previous bar if this beat starts a new one.
+    pub fn push_beat(&mut self, time: f64, bar: u64, beat_in_bar: usize) -> Option<BarGroove> {
+        let previous = match &mut self.bar {
+            Some((current, beats)) if *current == bar => {
+                beats.push(time);
+                return None;
+            }
+            _ => self.bar.take(),
+        };
+        // a bar is only measured from its downbeat, so the slots line up
+        if beat_in_bar == 0 {
+            self.bar = Some((bar, vec![time]));
+        }
+        let (previous, mut beats) = previous?;
+        beats.push(time);
+        // the onsets from half a slot before the bar belong to it
+        let half_slot = (time - beats[0]) / ((beats.len() - 1) * SLOTS_PER_BEAT * 2) as f64;
+        let onsets: Vec<f64> = self
+            .onsets
+            .iter()
+            .copied()
+            .filter(|onset| *onset >= beats[0] - half_slot && *onset < time - half_slot)
+            .collect();
+        self.onsets.retain(|onset| *onset >= time - half_slot);
+        let groove = measure(previous, &beats, &onsets)?;
+        self.bars.push(groove.clone());
+        Some(groove)
+    }
+
+    /// The groove of every bar so far (`None` before the first measured bar).
+    pub fn summary(&self) -> Option<GrooveSummary> {
+        if self.bars.is_empty() {
+            return None;
+        }
+        let swings: Vec<f32> = self.bars.iter().filter_map(|bar| bar.swing).collect();
+        let swung_bars = swings.len();
+        let swing = median(swings);
+        let sixteenths = self
+            .bars
+            .iter()
+            .filter(|bar| bar.swing.is_some() && bar.subdivision == 16)
+            .count();
+        let subdivision = if 2 * sixteenths > swung_bars { 16 } else { 8 };
+
+        // the bars of the most common length (e.g. 16 slots in 4/4)
+        let slots = most_common(self.bars.iter().map(|bar| bar.deviations.len()));
+        let mut sums = vec![(0.0, 0); slots];
+        for bar in self.bars.iter().filter(|bar| bar.deviations.len() == slots) {
+            for ((sum, count), deviation) in sums.iter_mut().zip(&bar.deviations) {
+                if let Some(deviation) = deviation {
+                    *sum += deviation;
+                    *count += 1;
+                }
+            }
+        }
+        Some(GrooveSummary {
+            swing,
+            subdivision,
+            deviations: sums
+                .into_iter()
+                .map(|(sum, count)| (count > 0).then(|| sum / count as f32))
+                .collect(),
+        })
+    }
+}
+
+// the groove of the bar between the first and the last of `beats` (the next downbeat)
+fn measure(bar: u64, beats: &[f64], onsets: &[f64]) -> Option<BarGroove> {
+    let intervals: Vec<f64> = beats.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let mean = intervals.iter().sum::<f64>() / intervals.len().max(1) as f64;
+    if intervals.is_empty()
+        || intervals
+            .iter()
+            .any(|interval| *interval <= 0.0 || *interval > MAX_INTERVAL_RATIO * mean)
+    {
+        return None;
+    }
+
+    let mut eighths = Vec::new();
+    let mut sixteenths = Vec::new();
+    let mut deviations = Vec::with_capacity(intervals.len() * SLOTS_PER_BEAT);
+    for (beat, interval) in beats.iter().zip(&intervals) {
+        eighths.extend(swing_ratio(onsets, *beat, *interval));
+        let half = interval / 2.0;
+        sixteenths.extend(swing_ratio(onsets, *beat, half));
+        sixteenths.extend(swing_ratio(onsets, beat + half, half));
+
+        // the deviation of the nearest onset within half a slot of each slot
+        let slot_length = interval / SLOTS_PER_BEAT as f64;
+        for slot in 0..SLOTS_PER_BEAT {
+            let grid = beat + slot as f64 * slot_length;
+            let deviation = onsets
+                .iter()
+                .map(|onset| onset - grid)
+                .filter(|deviation| deviation.abs() < slot_length / 2.0)
+                .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+            deviations.push(deviation.map(|deviation| (deviation * 1000.0) as f32));
+        }
+    }
+
+    // the off-beats of a sixteenth-note groove sit between the eighth notes
+    let use_sixteenths =
+        sixteenths.len() as f64 >= MIN_SIXTEENTH_OFF_BEATS * intervals.len() as f64;
+    let (ratios, subdivision) = if use_sixteenths {
+        (sixteenths, 16)
+    } else {
+        (eighths, 8)
+    };
+    Some(BarGroove {
+        bar,
+        start: beats[0],
+        end: beats[beats.len() - 1],
+        swing: median(ratios),
+        subdivision,
+        deviations,
+    })
+}
+
+// the swing ratio of the pair of notes from `start` that lasts `length`
+// (`None` without an off-beat)
+fn swing_ratio(onsets: &[f64], start: f64, length: f64) -> Option<f32> {
+    let (from, to) = (
+        start + OFF_BEAT_RANGE.0 * length,
+        start + OFF_BEAT_RANGE.1 * length,
+    );
+    let off_beat = onsets.iter().find(|onset| (from..to).contains(*onset))?;
+    // the notes on the grid are measured too, so a late or early grid does not bend the ratio
+    let on_beat = nearest_onset(onsets, start, length).unwrap_or(start);
+    let next = nearest_onset(onsets, start + length, length).unwrap_or(start + length);
+    Some(((off_beat - on_beat) / (next - off_beat)) as f32)
+}
+
+// the onset nearest `time` if it is closer than the grid tolerance of a pair of notes
+fn nearest_onset(onsets: &[f64], time: f64, length: f64) -> Option<f64> {
+    onsets
+        .iter()
+        .copied()
+        .filter(|onset| (onset - time).abs() < GRID_TOLERANCE * length)
+        .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    values.sort_by(f32::total_cmp);
+    values.get(values.len() / 2).copied()
+}
+
+fn most_common(values: impl Iterator<Item = usize>) -> usize {
+    let mut counts = std::collections::HashMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(value, count)| (*count, *value))
+        .map_or(0, |(value, _)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEAT: f64 = 0.5;
+
+    // the grooves of 4 bars of 4/4 at 120 BPM with onsets on every beat and at `off_beat` of
+    // every beat
+    fn play(off_beat: f64) -> (GrooveTracker, Vec<BarGroove>) {
+        let mut tracker = GrooveTracker::default();
+        tracker.push_onsets(
+            (0..17).flat_map(|beat| [beat as f64 * BEAT, (beat as f64 + off_beat) * BEAT]),
+        );
+        let grooves = (0..17)
+            .filter_map(|beat| tracker.push_beat(beat as f64 * BEAT, beat / 4, beat as usize % 4))
+            .collect();
+        (tracker, grooves)
+    }
+
+    #[test]
+    fn triplet_grid_swings_two_to_one() {
+        let (tracker, grooves) = play(2.0 / 3.0);
+        assert_eq!(grooves.len(), 4);
+        for groove in &grooves {
+            let swing = groove.swing.unwrap();
+            assert!((swing - 2.0).abs() < 1e-3, "bar {}: {swing}", groove.bar);
+            assert_eq!(groove.subdivision, 8);
+        }
+        let summary = tracker.summary().unwrap();
+        assert!((summary.swing.unwrap() - 2.0).abs() < 1e-3);
+        assert_eq!(summary.subdivision, 8);
+        // the triplet off-beat is 3/4 - 2/3 of a beat (42 ms) early on the fourth slot
+        assert_eq!(summary.deviations.len(), 16);
+        assert_eq!(summary.deviations[0], Some(0.0));
+        assert_eq!(summary.deviations[2], None);
+        let early = summary.deviations[3].unwrap();
+        assert!((early + 41.67).abs() < 0.1, "{early} ms");
+    }
+
+    #[test]
+    fn straight_grid_does_not_swing() {
+        let (tracker, grooves) = play(0.5);
+        assert_eq!(grooves.len(), 4);
+        let summary = tracker.summary().unwrap();
+        assert!((summary.swing.unwrap() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn no_summary_without_bars() {
+        assert!(GrooveTracker::default().summary().is_none());
+    }
+}
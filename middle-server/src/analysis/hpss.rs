@@ -0,0 +1,254 @@
+use crate::analysis::stft::{HOP_LENGTH, N_FFT, Stft, hann_window};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::{collections::VecDeque, sync::Arc};
+
+/// The length of the median filters in frames (across time) and in bins (across frequency),
+/// like the default of `librosa.decompose.hpss`.
+const KERNEL_SIZE: usize = 31;
+
+/// A signal split into its harmonic and percussive parts.
+pub struct Separation {
+    /// Sustained tones: pads, vocals, bass.
+    pub harmonic: Vec<f32>,
+    /// Transients: drums and attacks.
+    pub percussive: Vec<f32>,
+    /// The energy of each part (the sum of their squared STFT magnitudes).
+    pub harmonic_energy: f32,
+    pub percussive_energy: f32,
+}
+
+/// Split a mono signal into its harmonic and percussive parts (median filtering, Fitzgerald 2010).
+///
+/// Harmonic sounds are smooth across time and percussive sounds are smooth across frequency,
+/// so the median of each direction estimates one of them, and soft masks from the two estimates
+/// split the STFT.
+pub fn hpss(samples: &[f32], sample_rate: u32) -> Separation {
+    let stft = Stft::new(samples, sample_rate, N_FFT, HOP_LENGTH);
+    let magnitudes = stft.magnitudes();
+    let frames = magnitudes.len();
+    let bins = N_FFT / 2 + 1;
+    let half = KERNEL_SIZE / 2;
+
+    let mut window = Vec::with_capacity(KERNEL_SIZE);
+    let mut harmonic_frames = vec![vec![Complex::new(0.0, 0.0); bins]; frames];
+    let mut percussive_frames = harmonic_frames.clone();
+    let mut harmonic_energy = 0.0;
+    let mut percussive_energy = 0.0;
+    for t in 0..frames {
+        for bin in 0..bins {
+            window.clear();
+            window.extend(
+                magnitudes[t.saturating_sub(half)..(t + half + 1).min(frames)]
+                    .iter()
+                    .map(|frame| frame[bin]),
+            );
+            let harmonic = median(&mut window);
+            window.clear();
+            window.extend_from_slice(
+                &magnitudes[t][bin.saturating_sub(half)..(bin + half + 1).min(bins)],
+            );
+            let percussive = median(&mut window);
+
+            // Wiener-like soft masks (power 2)
+            let (harmonic, percussive) = (harmonic * harmonic, percussive * percussive);
+            let total = harmonic + percussive;
+            let harmonic_mask = if total > f32::EPSILON {
+                harmonic / total
+            } else {
+                0.5
+            };
+            let value = stft.frames[t][bin];
+            harmonic_frames[t][bin] = value * harmonic_mask;
+            percussive_frames[t][bin] = value * (1.0 - harmonic_mask);
+            harmonic_energy += harmonic_frames[t][bin].norm_sqr();
+            percussive_energy += percussive_frames[t][bin].norm_sqr();
+        }
+    }
+
+    Separation {
+        harmonic: stft.inverse(&harmonic_frames, samples.len()),
+        percussive: stft.inverse(&percussive_frames, samples.len()),
+        harmonic_energy,
+        percussive_energy,
+    }
+}
+
+/// Separates the percussive part of a mono stream as it arrives, like `hpss` does for a whole
+/// signal.
+///
+/// A frame is masked once the `KERNEL_SIZE / 2` frames after it have arrived, so the percussive
+/// part lags the input by those frames plus one FFT frame (about 0.2 s at 44.1 kHz). It starts
+/// at the start of the stream, so the frames of its samples are the frames of the input.
+pub struct PercussiveFilter {
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// The last `N_FFT` samples, starting with half a frame of silence like `Stft`.
+    input: VecDeque<f32>,
+    pending_samples: usize,
+    /// The spectra of the last `KERNEL_SIZE` frames and their magnitudes.
+    frames: VecDeque<(Vec<Complex<f32>>, Vec<f32>)>,
+    /// The number of frames masked so far.
+    masked_frames: usize,
+    /// The weighted overlap-add of the masked frames (signal, weight) from sample
+    /// `output_start` on (negative before the stream).
+    output: VecDeque<(f32, f32)>,
+    output_start: isize,
+}
+
+impl Default for PercussiveFilter {
+    fn default() -> Self {
+        let mut planner = FftPlanner::<f32>::new();
+        PercussiveFilter {
+            fft: planner.plan_fft_forward(N_FFT),
+            ifft: planner.plan_fft_inverse(N_FFT),
+            window: hann_window(N_FFT),
+            input: VecDeque::from(vec![0.0; N_FFT / 2]),
+            pending_samples: 0,
+            frames: VecDeque::new(),
+            masked_frames: 0,
+            output: VecDeque::from(vec![(0.0, 0.0); N_FFT]),
+            output_start: -((N_FFT / 2) as isize),
+        }
+    }
+}
+
+impl PercussiveFilter {
+    /// Separate samples that follow the previous ones, and return the percussive part of the
+    /// samples that are done (the ones no later frame overlaps), in order.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let mut percussive = Vec::new();
+        for sample in samples {
+            if self.input.len() == N_FFT {
+                self.input.pop_front();
+            }
+            self.input.push_back(*sample);
+            self.pending_samples += 1;
+            // the first frame is due once the buffer is full, the next ones every hop
+            if self.input.len() == N_FFT
+                && (self.frames.is_empty() || self.pending_samples >= HOP_LENGTH)
+            {
+                self.pending_samples = 0;
+                self.push_frame();
+                percussive.extend(self.mask_frame());
+            }
+        }
+        percussive
+    }
+
+    fn push_frame(&mut self) {
+        let mut buffer: Vec<Complex<f32>> = self
+            .input
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| Complex::new(sample * window, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        buffer.truncate(N_FFT / 2 + 1);
+        let magnitudes = buffer.iter().map(|value| value.norm()).collect();
+        if self.frames.len() == KERNEL_SIZE {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((buffer, magnitudes));
+    }
+
+    // mask the frame that has the whole kernel (or the start of the stream) before it and half
+    // a kernel after it, add it to the output and return the samples no later frame overlaps
+    fn mask_frame(&mut self) -> Vec<f32> {
+        let half = KERNEL_SIZE / 2;
+        let Some(center) = self.frames.len().checked_sub(half + 1) else {
+            return Vec::new();
+        };
+        let bins = N_FFT / 2 + 1;
+        let (spectrum, magnitudes) = &self.frames[center];
+        let mut window = Vec::with_capacity(KERNEL_SIZE);
+        let mut buffer = vec![Complex::new(0.0, 0.0); N_FFT];
+        for bin in 0..bins {
+            window.clear();
+            window.extend(self.frames.iter().map(|(_, frame)| frame[bin]));
+            let harmonic = median(&mut window);
+            window.clear();
+            window.extend_from_slice(
+                &magnitudes[bin.saturating_sub(half)..(bin + half + 1).min(bins)],
+            );
+            let percussive = median(&mut window);
+
+            // the same soft mask as `hpss`
+            let (harmonic, percussive) = (harmonic * harmonic, percussive * percussive);
+            let total = harmonic + percussive;
+            let percussive_mask = if total > f32::EPSILON {
+                percussive / total
+            } else {
+                0.5
+            };
+            buffer[bin] = spectrum[bin] * percussive_mask;
+        }
+        // the negative frequencies are the conjugates of the positive ones
+        for bin in bins..N_FFT {
+            buffer[bin] = buffer[N_FFT - bin].conj();
+        }
+        self.ifft.process(&mut buffer);
+
+        // the frame starts half a frame before its center
+        let offset = (self.masked_frames * HOP_LENGTH) as isize - (N_FFT / 2) as isize;
+        self.masked_frames += 1;
+        let start = (offset - self.output_start) as usize;
+        if self.output.len() < start + N_FFT {
+            self.output.resize(start + N_FFT, (0.0, 0.0));
+        }
+        for (n, value) in buffer.iter().enumerate() {
+            let (signal, weight) = &mut self.output[start + n];
+            *signal += value.re / N_FFT as f32 * self.window[n];
+            *weight += self.window[n] * self.window[n];
+        }
+
+        // the next frame starts a hop later, so everything before it is done
+        let done = start + HOP_LENGTH;
+        let mut percussive = Vec::with_capacity(HOP_LENGTH);
+        for (index, (signal, weight)) in self.output.drain(..done).enumerate() {
+            // the samples before the stream are only there to be overlapped
+            if self.output_start + index as isize >= 0 {
+                percussive.push(if weight > f32::EPSILON {
+                    signal / weight
+                } else {
+                    0.0
+                });
+            }
+        }
+        self.output_start += done as isize;
+        percussive
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    let middle = values.len() / 2;
+    *values.select_nth_unstable_by(middle, f32::total_cmp).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percussive_filter_matches_hpss() {
+        // a tone with a click every 5000 samples
+        let samples: Vec<f32> = (0..44100)
+            .map(|n| 0.3 * (n as f32 * 0.05).sin() + if n % 5000 == 0 { 0.8 } else { 0.0 })
+            .collect();
+        let expected = hpss(&samples, 44100).percussive;
+        let mut filter = PercussiveFilter::default();
+        let streamed: Vec<f32> = samples
+            .chunks(1000)
+            .flat_map(|chunk| filter.process(chunk))
+            .collect();
+        // the last samples wait for the frames after them
+        assert!(
+            streamed.len() > samples.len() / 2,
+            "{} samples",
+            streamed.len()
+        );
+        for (n, (streamed, expected)) in streamed.iter().zip(&expected).enumerate() {
+            assert!((streamed - expected).abs() < 1e-4, "sample {n}");
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::analysis::chroma::PITCH_CLASSES;
+use std::fmt;
+
+/// The Krumhansl-Kessler major key profile, starting at the tonic.
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+/// The Krumhansl-Kessler minor key profile, starting at the tonic.
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A musical key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    /// The pitch class of the tonic (0 = C).
+    pub tonic: usize,
+    pub mode: Mode,
+}
+
+impl Key {
+    /// The key in Camelot notation, e.g. `8B` for C major and `8A` for A minor.
+    pub fn camelot(&self) -> String {
+        // relative keys share the number, which follows the circle of fifths from C = 8
+        let major_tonic = match self.mode {
+            Mode::Major => self.tonic,
+            Mode::Minor => (self.tonic + 3) % 12,
+        };
+        let number = (major_tonic * 7 + 7) % 12 + 1;
+        let letter = match self.mode {
+            Mode::Major => 'B',
+            Mode::Minor => 'A',
+        };
+        format!("{number}{letter}")
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(f, "{} {}", PITCH_CLASSES[self.tonic], mode)
+    }
+}
+
+/// A key with how well the chroma matches its profile.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyEstimate {
+    pub key: Key,
+    /// The correlation of the chroma with the key profile, from -1.0 to 1.0.
+    pub correlation: f32,
+}
+
+/// Estimate the key of a chroma vector (Krumhansl-Schmuckler).
+///
+/// Returns `None` for a flat chroma (e.g. silence or unpitched noise).
+pub fn estimate_key(chroma: &[f32; 12]) -> Option<KeyEstimate> {
+    [(Mode::Major, &MAJOR_PROFILE), (Mode::Minor, &MINOR_PROFILE)]
+        .into_iter()
+        .flat_map(|(mode, profile)| {
+            (0..12).filter_map(move |tonic| {
+                let rotated: Vec<f32> = (0..12).map(|pc| profile[(pc + 12 - tonic) % 12]).collect();
+                correlation(chroma, &rotated).map(|correlation| KeyEstimate {
+                    key: Key { tonic, mode },
+                    correlation,
+                })
+            })
+        })
+        .max_by(|a, b| a.correlation.total_cmp(&b.correlation))
+}
+
+// Pearson correlation, `None` if either side is constant
+fn correlation(a: &[f32], b: &[f32]) -> Option<f32> {
+    let mean_a = a.iter().sum::<f32>() / a.len() as f32;
+    let mean_b = b.iter().sum::<f32>() / b.len() as f32;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a) * (x - mean_a);
+        variance_b += (y - mean_b) * (y - mean_b);
+    }
+    let denominator = (variance_a * variance_b).sqrt();
+    (denominator > f32::EPSILON).then(|| covariance / denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::chroma::{chromagram, mean_chroma};
+
+    #[test]
+    fn camelot_follows_the_circle_of_fifths() {
+        let key = |tonic, mode| Key { tonic, mode }.camelot();
+        assert_eq!(key(0, Mode::Major), "8B");
+        assert_eq!(key(9, Mode::Minor), "8A");
+        assert_eq!(key(7, Mode::Major), "9B");
+        assert_eq!(key(4, Mode::Minor), "9A");
+        assert_eq!(key(5, Mode::Major), "7B");
+        assert_eq!(key(2, Mode::Minor), "7A");
+        // B major wraps around to 1B
+        assert_eq!(key(11, Mode::Major), "1B");
+        assert_eq!(key(6, Mode::Major), "2B");
+    }
+
+    #[test]
+    fn c_major_triad_is_in_c_major() {
+        // C4, E4 and G4 for 2 s
+        let sample_rate = 22050;
+        let samples: Vec<f32> = (0..sample_rate * 2)
+            .map(|n| {
+                let time = n as f32 / sample_rate as f32;
+                [261.63, 329.63, 392.0]
+                    .iter()
+                    .map(|frequency| 0.2 * (2.0 * std::f32::consts::PI * frequency * time).sin())
+                    .sum()
+            })
+            .collect();
+        let estimate = estimate_key(&mean_chroma(&chromagram(&samples, sample_rate))).unwrap();
+        assert_eq!(
+            estimate.key,
+            Key {
+                tonic: 0,
+                mode: Mode::Major
+            }
+        );
+        assert!(estimate.correlation > 0.5, "{}", estimate.correlation);
+    }
+
+    #[test]
+    fn silence_has_no_key() {
+        assert!(estimate_key(&[0.0; 12]).is_none());
+    }
+}
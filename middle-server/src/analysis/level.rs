@@ -0,0 +1,15 @@
+/// Windows quieter than this (-60 dBFS) are silent.
+pub const SILENCE_RMS: f32 = 0.001;
+
+/// The root mean square of `samples`.
+pub fn root_mean_square(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Whether `samples` are below the silence threshold.
+pub fn is_silent(samples: &[f32]) -> bool {
+    root_mean_square(samples) < SILENCE_RMS
+}
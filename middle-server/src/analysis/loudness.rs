@@ -0,0 +1,386 @@
+use std::collections::VecDeque;
+
+/// The length of the momentary loudness window in seconds (EBU R128).
+const MOMENTARY_SECONDS: f64 = 0.4;
+/// The length of the short-term loudness window in seconds (EBU R128).
+const SHORT_TERM_SECONDS: f64 = 3.0;
+/// The floor of every level in dB, instead of minus infinity for silence.
+pub const MIN_DB: f32 = -120.0;
+/// The oversampling factor of the true-peak meter (ITU-R BS.1770-4, Annex 2).
+const OVERSAMPLING: usize = 4;
+/// The taps of each phase of the true-peak interpolation filter.
+const TAPS_PER_PHASE: usize = 12;
+/// A sample at or above this level clips: the largest positive 16-bit sample, which the wider
+/// formats reach too.
+const CLIPPING_LEVEL: f32 = 32767.0 / 32768.0;
+
+/// The levels of one block of a stream.
+#[derive(Debug, Clone)]
+pub struct LoudnessReading {
+    /// The RMS of each channel in dBFS.
+    pub rms: Vec<f32>,
+    /// The largest sample of each channel in dBFS.
+    pub sample_peak: Vec<f32>,
+    /// The largest 4x oversampled sample of each channel in dBTP.
+    pub true_peak: Vec<f32>,
+    /// The loudness of the last 400 ms in LUFS, `None` until 400 ms have been measured.
+    pub momentary: Option<f32>,
+    /// The loudness of the last 3 s in LUFS, `None` until 3 s have been measured.
+    pub short_term: Option<f32>,
+    /// Whether any sample reached full scale (the largest positive 16-bit sample or more).
+    pub clipping: bool,
+}
+
+/// Measures RMS, peaks and loudness (ITU-R BS.1770 / EBU R128) of an interleaved stream,
+/// one reading per block of `block_frames` frames.
+pub struct LoudnessMeter {
+    channels: usize,
+    block_frames: usize,
+    momentary_blocks: usize,
+    short_term_blocks: usize,
+    /// The K-weighting filter of each channel.
+    k_filters: Vec<[Biquad; 2]>,
+    true_peak_meters: Vec<TruePeakMeter>,
+    /// The mean square of the K-weighted samples of each channel, for the recent blocks.
+    block_powers: VecDeque<Vec<f64>>,
+    block: Block,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, sample_rate: u32, block_frames: usize) -> Self {
+        let block_frames = block_frames.max(1);
+        let blocks = |seconds: f64| {
+            ((seconds * sample_rate as f64 / block_frames as f64).round() as usize).max(1)
+        };
+        LoudnessMeter {
+            channels,
+            block_frames,
+            momentary_blocks: blocks(MOMENTARY_SECONDS),
+            short_term_blocks: blocks(SHORT_TERM_SECONDS),
+            k_filters: (0..channels).map(|_| k_weighting(sample_rate)).collect(),
+            true_peak_meters: (0..channels).map(|_| TruePeakMeter::new()).collect(),
+            block_powers: VecDeque::new(),
+            block: Block::new(channels),
+        }
+    }
+
+    /// Measure interleaved samples, returning a reading for every block they complete.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<LoudnessReading> {
+        let mut readings = Vec::new();
+        if self.channels == 0 {
+            return readings;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let [shelf, high_pass] = &mut self.k_filters[channel];
+                let weighted = high_pass.process(shelf.process(sample as f64));
+                let true_peak = self.true_peak_meters[channel].process(sample);
+                self.block.add(channel, sample, weighted, true_peak);
+            }
+            self.block.frames += 1;
+            if self.block.frames == self.block_frames {
+                readings.push(self.finish_block());
+            }
+        }
+        readings
+    }
+
+    fn finish_block(&mut self) -> LoudnessReading {
+        let block = std::mem::replace(&mut self.block, Block::new(self.channels));
+        let frames = block.frames as f64;
+
+        self.block_powers.push_back(
+            block
+                .weighted_square_sums
+                .iter()
+                .map(|sum| sum / frames)
+                .collect(),
+        );
+        if self.block_powers.len() > self.short_term_blocks {
+            self.block_powers.pop_front();
+        }
+
+        LoudnessReading {
+            rms: block
+                .square_sums
+                .iter()
+                .map(|sum| power_to_db(sum / frames))
+                .collect(),
+            sample_peak: block
+                .sample_peaks
+                .iter()
+                .map(|peak| amplitude_to_db(*peak))
+                .collect(),
+            true_peak: block
+                .true_peaks
+                .iter()
+                .map(|peak| amplitude_to_db(*peak))
+                .collect(),
+            momentary: self.loudness(self.momentary_blocks),
+            short_term: self.loudness(self.short_term_blocks),
+            clipping: block
+                .sample_peaks
+                .iter()
+                .any(|peak| *peak >= CLIPPING_LEVEL),
+        }
+    }
+
+    // the loudness of the last `blocks` blocks in LUFS
+    fn loudness(&self, blocks: usize) -> Option<f32> {
+        if self.block_powers.len() < blocks {
+            return None;
+        }
+        let recent = self
+            .block_powers
+            .iter()
+            .skip(self.block_powers.len() - blocks);
+        let mut channel_powers = vec![0.0; self.channels];
+        for powers in recent {
+            for (sum, power) in channel_powers.iter_mut().zip(powers) {
+                *sum += power / blocks as f64;
+            }
+        }
+        let power: f64 = channel_powers
+            .iter()
+            .enumerate()
+            .map(|(channel, power)| channel_weight(channel, self.channels) * power)
+            .sum();
+        Some(if power > 0.0 {
+            (-0.691 + 10.0 * power.log10()).max(MIN_DB as f64) as f32
+        } else {
+            MIN_DB
+        })
+    }
+}
+
+// the running sums of the current block
+struct Block {
+    frames: usize,
+    square_sums: Vec<f64>,
+    weighted_square_sums: Vec<f64>,
+    sample_peaks: Vec<f32>,
+    true_peaks: Vec<f32>,
+}
+
+impl Block {
+    fn new(channels: usize) -> Self {
+        Block {
+            frames: 0,
+            square_sums: vec![0.0; channels],
+            weighted_square_sums: vec![0.0; channels],
+            sample_peaks: vec![0.0; channels],
+            true_peaks: vec![0.0; channels],
+        }
+    }
+
+    fn add(&mut self, channel: usize, sample: f32, weighted: f64, true_peak: f32) {
+        self.square_sums[channel] += (sample as f64) * (sample as f64);
+        self.weighted_square_sums[channel] += weighted * weighted;
+        self.sample_peaks[channel] = self.sample_peaks[channel].max(sample.abs());
+        self.true_peaks[channel] = self.true_peaks[channel].max(true_peak);
+    }
+}
+
+// BS.1770 channel weights: the LFE channel of 5.1 is ignored, the surround channels count more
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6.., 3) => 0.0,
+        (6.., 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn power_to_db(power: f64) -> f32 {
+    if power > 0.0 {
+        (10.0 * power.log10()).max(MIN_DB as f64) as f32
+    } else {
+        MIN_DB
+    }
+}
+
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+/// A biquad filter (direct form I).
+#[derive(Debug, Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+// the K-weighting of BS.1770 (a high shelf and a high pass) at any sample rate
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let sample_rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// The largest absolute value of a channel after 4x oversampling.
+struct TruePeakMeter {
+    /// `phases[p][k]` is tap `k` of the polyphase filter that interpolates phase `p`.
+    phases: Vec<[f32; TAPS_PER_PHASE]>,
+    /// The last `TAPS_PER_PHASE` samples, newest first.
+    history: VecDeque<f32>,
+}
+
+impl TruePeakMeter {
+    fn new() -> Self {
+        // windowed sinc low pass at the original Nyquist frequency
+        let length = OVERSAMPLING * TAPS_PER_PHASE;
+        let center = (length / 2) as f32;
+        let taps: Vec<f32> = (0..length)
+            .map(|n| {
+                let x = (n as f32 - center) / OVERSAMPLING as f32;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (std::f32::consts::PI * x).sin() / (std::f32::consts::PI * x)
+                };
+                let window =
+                    0.5 - 0.5 * (2.0 * std::f32::consts::PI * n as f32 / length as f32).cos();
+                sinc * window
+            })
+            .collect();
+        let phases = (0..OVERSAMPLING)
+            .map(|phase| std::array::from_fn(|k| taps[phase + k * OVERSAMPLING]))
+            .collect();
+        TruePeakMeter {
+            phases,
+            history: VecDeque::from(vec![0.0; TAPS_PER_PHASE]),
+        }
+    }
+
+    // the peak of the oversampled signal between the previous sample and `sample`
+    fn process(&mut self, sample: f32) -> f32 {
+        self.history.pop_back();
+        self.history.push_front(sample);
+        self.phases
+            .iter()
+            .map(|taps| {
+                taps.iter()
+                    .zip(&self.history)
+                    .map(|(tap, sample)| tap * sample)
+                    .sum::<f32>()
+                    .abs()
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::applications::window::binary_transformer;
+    use common::audio::{AudioInfo, SampleFormat};
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    // the last reading of `samples` measured in 100 ms blocks
+    fn measure(channels: usize, samples: &[f32]) -> LoudnessReading {
+        let mut meter = LoudnessMeter::new(channels, SAMPLE_RATE, SAMPLE_RATE as usize / 10);
+        meter.process(samples).pop().expect("no complete block")
+    }
+
+    // 4 s of a stereo signal with the same sample on both channels
+    fn stereo(sample: impl Fn(f32) -> f32) -> Vec<f32> {
+        (0..SAMPLE_RATE * 4)
+            .flat_map(|n| {
+                let value = sample(n as f32 / SAMPLE_RATE as f32);
+                [value, value]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sine_at_minus_20_dbfs_measures_minus_20_lufs() {
+        // EBU Tech 3341: a stereo 1 kHz sine at -20 dBFS reads -20 LUFS
+        let amplitude = 10f32.powf(-20.0 / 20.0);
+        let reading = measure(
+            2,
+            &stereo(|time| amplitude * (2.0 * std::f32::consts::PI * 1000.0 * time).sin()),
+        );
+        let momentary = reading.momentary.unwrap();
+        let short_term = reading.short_term.unwrap();
+        assert!((momentary + 20.0).abs() < 0.1, "momentary {momentary} LUFS");
+        assert!(
+            (short_term + 20.0).abs() < 0.1,
+            "short-term {short_term} LUFS"
+        );
+        assert!(!reading.clipping);
+    }
+
+    // a 16-bit stereo square wave of 100 Hz between `low` and `high`, decoded like the stream
+    fn s16_square(low: i16, high: i16) -> Vec<f32> {
+        let binary: Vec<u8> = (0..SAMPLE_RATE)
+            .flat_map(|n| {
+                let value = if (n / 240) % 2 == 0 { high } else { low };
+                [value.to_le_bytes(), value.to_le_bytes()].concat()
+            })
+            .collect();
+        binary_transformer(binary, &AudioInfo::new(2, SAMPLE_RATE, SampleFormat::S16le))
+    }
+
+    #[test]
+    fn full_scale_s16_clips() {
+        assert!(measure(2, &s16_square(i16::MIN, i16::MAX)).clipping);
+        // positive full scale alone clips too
+        assert!(measure(2, &s16_square(0, i16::MAX)).clipping);
+    }
+
+    #[test]
+    fn s16_below_full_scale_does_not_clip() {
+        assert!(!measure(2, &s16_square(-32000, 32000)).clipping);
+    }
+}
@@ -0,0 +1,253 @@
+use serde::Serialize;
+use std::{collections::VecDeque, fmt};
+
+/// The number of recent beats the meter and the downbeats are estimated from.
+const ACCENT_HISTORY: usize = 48;
+/// The meter is reported once this many beats have been seen.
+const MIN_BEATS: usize = 12;
+/// 3/4 has to group the accents this much better than 4/4 (4/4 is far more common).
+const TRIPLE_METER_MARGIN: f32 = 1.2;
+/// The beats have to be split in three this much more clearly than in two for 6/8.
+const COMPOUND_MARGIN: f32 = 1.5;
+/// How fast the subdivision evidence of old windows fades.
+const SUBDIVISION_DECAY: f32 = 0.9;
+
+/// A time signature, with the beats as the beat tracker counts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Meter {
+    /// Three beats per bar, each split in two.
+    #[serde(rename = "3/4")]
+    ThreeFour,
+    /// Four beats per bar, each split in two.
+    #[serde(rename = "4/4")]
+    FourFour,
+    /// Two (dotted quarter) beats per bar, each split in three.
+    #[serde(rename = "6/8")]
+    SixEight,
+}
+
+impl Meter {
+    pub fn beats_per_bar(self) -> usize {
+        match self {
+            Meter::ThreeFour => 3,
+            Meter::FourFour => 4,
+            Meter::SixEight => 2,
+        }
+    }
+}
+
+impl fmt::Display for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Meter::ThreeFour => "3/4",
+            Meter::FourFour => "4/4",
+            Meter::SixEight => "6/8",
+        })
+    }
+}
+
+/// How strongly the beats of a window are split in two and in three.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Subdivision {
+    pub duple: f32,
+    pub triple: f32,
+}
+
+/// Measure the subdivision of `beats` (in seconds) from the onset strength envelope.
+///
+/// The envelope halfway between two beats is compared with the envelope a third and two thirds
+/// of the way, both relative to the envelope a sixth of the way from each beat.
+pub fn subdivision(envelope: &[f32], frame_rate: f32, beats: &[f64]) -> Subdivision {
+    // the peak of the envelope within a frame of `time`
+    let peak = |time: f64| {
+        let frame = (time * frame_rate as f64).round() as usize;
+        let end = (frame + 2).min(envelope.len());
+        envelope[frame.saturating_sub(1).min(end)..end]
+            .iter()
+            .copied()
+            .fold(0.0, f32::max)
+    };
+    let mean = envelope.iter().sum::<f32>() / envelope.len().max(1) as f32;
+    if mean <= f32::EPSILON {
+        return Subdivision::default();
+    }
+
+    let mut subdivision = Subdivision::default();
+    for pair in beats.windows(2) {
+        let (beat, period) = (pair[0], pair[1] - pair[0]);
+        let at = |fraction: f64| peak(beat + period * fraction);
+        let baseline = (at(1.0 / 6.0) + at(5.0 / 6.0)) / 2.0;
+        subdivision.duple += (at(0.5) - baseline).max(0.0) / mean;
+        subdivision.triple += ((at(1.0 / 3.0) + at(2.0 / 3.0)) / 2.0 - baseline).max(0.0) / mean;
+    }
+    subdivision
+}
+
+/// The position of a beat in the bars of the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarPosition {
+    /// The bar of the beat, counted from 0 (bar 0 may be a partial bar).
+    pub bar: u64,
+    /// The beat within the bar, counted from 0 (0 is the downbeat).
+    pub beat_in_bar: usize,
+}
+
+/// Estimates the meter of a stream and counts its bars, beat by beat.
+///
+/// The downbeats are the beats every bar with the strongest accent (kick drum and bass), and
+/// the meter is the grouping of the accents that stands out the most. 6/8 is told apart by
+/// beats that are split in three.
+#[derive(Debug, Default)]
+pub struct MeterTracker {
+    /// The accents of the recent beats.
+    accents: VecDeque<f32>,
+    /// The number of beats so far.
+    beat_count: u64,
+    subdivision: Subdivision,
+    meter: Option<Meter>,
+    /// The position of the last beat.
+    position: Option<BarPosition>,
+}
+
+impl MeterTracker {
+    /// The meter of the stream, `None` until enough beats have been seen.
+    pub fn meter(&self) -> Option<Meter> {
+        self.meter
+    }
+
+    /// Add the subdivision of the next window.
+    pub fn observe(&mut self, subdivision: Subdivision) {
+        self.subdivision = Subdivision {
+            duple: self.subdivision.duple * SUBDIVISION_DECAY + subdivision.duple,
+            triple: self.subdivision.triple * SUBDIVISION_DECAY + subdivision.triple,
+        };
+    }
+
+    /// Add the next beat with its accent, and return its position in the bars.
+    pub fn push(&mut self, accent: f32) -> BarPosition {
+        if self.accents.len() == ACCENT_HISTORY {
+            self.accents.pop_front();
+        }
+        self.accents.push_back(accent);
+        let index = self.beat_count;
+        self.beat_count += 1;
+
+        // 4/4 is assumed until the meter is known
+        let (meter, phase) = self.estimate();
+        if self.accents.len() >= MIN_BEATS {
+            self.meter = Some(meter);
+        }
+        let beats_per_bar = meter.beats_per_bar() as u64;
+        let beat_in_bar = ((index + beats_per_bar - phase) % beats_per_bar) as usize;
+        let bar = match self.position {
+            // a new bar starts when the count wraps (or the downbeat moves)
+            Some(last) if beat_in_bar > last.beat_in_bar => last.bar,
+            Some(last) => last.bar + 1,
+            None => 0,
+        };
+        *self.position.insert(BarPosition { bar, beat_in_bar })
+    }
+
+    // the meter and the phase of its downbeats (relative to beat 0 of the stream)
+    fn estimate(&self) -> (Meter, u64) {
+        let (three, three_phase) = self.grouping(3);
+        let (four, four_phase) = self.grouping(4);
+        if self.subdivision.triple > COMPOUND_MARGIN * self.subdivision.duple {
+            let (_, two_phase) = self.grouping(2);
+            (Meter::SixEight, two_phase)
+        } else if three > TRIPLE_METER_MARGIN * four {
+            (Meter::ThreeFour, three_phase)
+        } else {
+            (Meter::FourFour, four_phase)
+        }
+    }
+
+    // how much the accents stand out every `beats_per_bar` beats, and the phase where they do
+    fn grouping(&self, beats_per_bar: u64) -> (f32, u64) {
+        let first = self.beat_count - self.accents.len() as u64;
+        let mut sums = vec![(0.0f32, 0usize); beats_per_bar as usize];
+        for (offset, accent) in self.accents.iter().enumerate() {
+            let sum = &mut sums[((first + offset as u64) % beats_per_bar) as usize];
+            sum.0 += accent;
+            sum.1 += 1;
+        }
+        let total = self.accents.iter().sum::<f32>();
+        sums.iter()
+            .enumerate()
+            .filter(|(_, (_, count))| *count > 0 && *count < self.accents.len())
+            .map(|(phase, &(sum, count))| {
+                let others = (total - sum) / (self.accents.len() - count) as f32;
+                (sum / count as f32 - others, phase as u64)
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap_or((0.0, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the positions of `beats` beats with the accents of `pattern` (repeated)
+    fn track(tracker: &mut MeterTracker, pattern: &[f32], beats: usize) -> Vec<BarPosition> {
+        (0..beats)
+            .map(|beat| tracker.push(pattern[beat % pattern.len()]))
+            .collect()
+    }
+
+    #[test]
+    fn no_meter_before_enough_beats() {
+        let mut tracker = MeterTracker::default();
+        track(&mut tracker, &[2.0, 1.0, 1.0, 1.0], MIN_BEATS - 1);
+        assert_eq!(tracker.meter(), None);
+        tracker.push(2.0);
+        assert_eq!(tracker.meter(), Some(Meter::FourFour));
+    }
+
+    #[test]
+    fn accent_every_fourth_beat_is_four_four() {
+        // the stream starts on the last beat of a bar
+        let mut tracker = MeterTracker::default();
+        let positions = track(&mut tracker, &[1.0, 2.0, 1.0, 1.0], 32);
+        assert_eq!(tracker.meter(), Some(Meter::FourFour));
+        // the first beat comes before any accent to compare it with
+        for (index, position) in positions.iter().enumerate().skip(1) {
+            assert_eq!(
+                *position,
+                BarPosition {
+                    bar: (index as u64).div_ceil(4),
+                    beat_in_bar: (index + 3) % 4,
+                },
+                "beat {index}"
+            );
+        }
+    }
+
+    #[test]
+    fn accent_every_third_beat_is_three_four() {
+        let mut tracker = MeterTracker::default();
+        let positions = track(&mut tracker, &[2.0, 1.0, 1.0], 24);
+        assert_eq!(tracker.meter(), Some(Meter::ThreeFour));
+        let downbeats: Vec<usize> = positions
+            .iter()
+            .enumerate()
+            .skip(MIN_BEATS)
+            .filter(|(_, position)| position.beat_in_bar == 0)
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(downbeats, vec![12, 15, 18, 21]);
+    }
+
+    #[test]
+    fn beats_split_in_three_are_six_eight() {
+        let mut tracker = MeterTracker::default();
+        tracker.observe(Subdivision {
+            duple: 0.2,
+            triple: 1.0,
+        });
+        let positions = track(&mut tracker, &[2.0, 1.0], 16);
+        assert_eq!(tracker.meter(), Some(Meter::SixEight));
+        assert_eq!(positions[15].beat_in_bar, 1);
+        assert_eq!(positions[15].bar, 7);
+    }
+}
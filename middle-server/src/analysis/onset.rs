@@ -0,0 +1,221 @@
+use crate::analysis::stft::hann_window;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use serde::Serialize;
+use std::{collections::VecDeque, ops::Range, sync::Arc};
+
+/// The FFT size of the onset detector (23 ms at 44.1 kHz).
+const N_FFT: usize = 1024;
+/// The hop of the onset detector (5.8 ms at 44.1 kHz), which is also its time resolution.
+const HOP_LENGTH: usize = 256;
+/// The number of recent flux values the adaptive threshold looks at.
+const THRESHOLD_HISTORY: usize = 16;
+/// The flux has to exceed the median of the recent flux by this factor.
+const THRESHOLD_MULTIPLIER: f32 = 1.5;
+/// The flux also has to exceed this fraction of the recent peak flux.
+const THRESHOLD_PEAK_FRACTION: f32 = 0.1;
+/// The shortest time between two onsets in seconds.
+const MIN_ONSET_GAP: f32 = 0.05;
+/// How fast the peak flux decays per hop, so a loud passage does not mask a quiet one for long.
+const PEAK_DECAY: f32 = 0.999;
+/// The edges of the kick, snare and hi-hat bands in Hz.
+const KICK_BAND: Range<f32> = 30.0..150.0;
+const SNARE_BAND: Range<f32> = 150.0..2500.0;
+const HIHAT_BAND: Range<f32> = 5000.0..16000.0;
+/// A kick rises in the kick band this much more than in the hi-hat band.
+const KICK_DOMINANCE: f32 = 2.0;
+
+/// The percussive instrument an onset most likely comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Instrument {
+    Kick,
+    Snare,
+    HiHat,
+}
+
+/// An onset on the stream timeline.
+#[derive(Debug, Clone, Copy)]
+pub struct Onset {
+    /// The frame of the onset, counted from the start of the stream.
+    pub frame: u64,
+    /// The strength of the onset relative to the recent peaks, from 0.0 to 1.0.
+    pub strength: f32,
+    /// The instrument guessed from the bands that rise the most.
+    pub instrument: Instrument,
+}
+
+/// Detects onsets in an interleaved stream as it arrives (spectral flux with an adaptive
+/// threshold).
+///
+/// Onsets are reported one hop after they happen.
+pub struct OnsetDetector {
+    channels: usize,
+    sample_rate: u32,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// The bins of the kick, snare and hi-hat bands.
+    bands: [Range<usize>; 3],
+    /// The last `N_FFT` mono samples.
+    buffer: VecDeque<f32>,
+    pending_frames: usize,
+    /// The frame after the last sample in `buffer`.
+    end_frame: u64,
+    /// The log magnitude of the previous FFT frame.
+    previous: Vec<f32>,
+    /// The recent total flux, for the adaptive threshold.
+    history: VecDeque<f32>,
+    /// The decaying peak of the total flux.
+    peak: f32,
+    /// The last two hops (frame, total flux, band flux), to find the local maxima.
+    candidates: VecDeque<(u64, f32, [f32; 3])>,
+    last_onset: Option<u64>,
+}
+
+impl OnsetDetector {
+    /// A detector for a stream that starts at `start_frame`.
+    pub fn new(channels: usize, sample_rate: u32, start_frame: u64) -> Self {
+        let bin_width = sample_rate as f32 / N_FFT as f32;
+        let band = |range: Range<f32>| {
+            let start = ((range.start / bin_width).ceil() as usize).max(1);
+            let end = ((range.end / bin_width).ceil() as usize).clamp(start + 1, N_FFT / 2 + 1);
+            start.min(N_FFT / 2)..end
+        };
+        OnsetDetector {
+            channels,
+            sample_rate,
+            fft: FftPlanner::<f32>::new().plan_fft_forward(N_FFT),
+            window: hann_window(N_FFT),
+            bands: [band(KICK_BAND), band(SNARE_BAND), band(HIHAT_BAND)],
+            buffer: VecDeque::from(vec![0.0; N_FFT]),
+            pending_frames: 0,
+            end_frame: start_frame,
+            previous: vec![0.0; N_FFT / 2 + 1],
+            history: VecDeque::new(),
+            peak: 0.0,
+            candidates: VecDeque::new(),
+            last_onset: None,
+        }
+    }
+
+    /// Restart the detection at `start_frame`, e.g. after a gap in the stream.
+    pub fn reset(&mut self, start_frame: u64) {
+        *self = OnsetDetector::new(self.channels, self.sample_rate, start_frame);
+    }
+
+    /// Detect the onsets in interleaved samples that follow the previous ones.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<Onset> {
+        let mut onsets = Vec::new();
+        if self.channels == 0 {
+            return onsets;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            self.buffer.pop_front();
+            self.buffer
+                .push_back(frame.iter().sum::<f32>() / self.channels as f32);
+            self.end_frame += 1;
+            self.pending_frames += 1;
+            if self.pending_frames == HOP_LENGTH {
+                self.pending_frames = 0;
+                onsets.extend(self.hop());
+            }
+        }
+        onsets
+    }
+
+    fn hop(&mut self) -> Option<Onset> {
+        let mut buffer: Vec<Complex<f32>> = self
+            .buffer
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| Complex::new(sample * window, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        let magnitudes: Vec<f32> = buffer[..=N_FFT / 2]
+            .iter()
+            .map(|bin| (1.0 + 100.0 * bin.norm()).ln())
+            .collect();
+
+        // half-wave rectified rise of the log magnitude, per band
+        let rise: Vec<f32> = magnitudes
+            .iter()
+            .zip(&self.previous)
+            .map(|(current, previous)| (current - previous).max(0.0))
+            .collect();
+        self.previous = magnitudes;
+        let band_flux = self
+            .bands
+            .clone()
+            .map(|bins| rise[bins.clone()].iter().sum::<f32>() / bins.len() as f32);
+        // every band counts the same, so a kick is not drowned out by the many high bins
+        let flux = band_flux.iter().sum::<f32>();
+
+        // the onset is stamped at the center of the FFT frame
+        let frame = self.end_frame.saturating_sub((N_FFT / 2) as u64);
+        self.candidates.push_back((frame, flux, band_flux));
+        let onset = if self.candidates.len() == 3 {
+            let (before, (frame, flux, band_flux), after) = (
+                self.candidates[0].1,
+                self.candidates[1],
+                self.candidates[2].1,
+            );
+            self.pick(frame, flux, band_flux, before, after)
+        } else {
+            None
+        };
+        if self.candidates.len() == 3 {
+            self.candidates.pop_front();
+        }
+
+        self.peak = (self.peak * PEAK_DECAY).max(flux);
+        if self.history.len() == THRESHOLD_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(flux);
+        onset
+    }
+
+    // an onset if `flux` is a local maximum above the adaptive threshold
+    fn pick(
+        &mut self,
+        frame: u64,
+        flux: f32,
+        band_flux: [f32; 3],
+        before: f32,
+        after: f32,
+    ) -> Option<Onset> {
+        if flux <= before || flux < after {
+            return None;
+        }
+        let mut recent: Vec<f32> = self.history.iter().copied().collect();
+        recent.sort_by(f32::total_cmp);
+        let median = recent.get(recent.len() / 2).copied().unwrap_or(0.0);
+        let threshold = (THRESHOLD_MULTIPLIER * median).max(THRESHOLD_PEAK_FRACTION * self.peak);
+        if flux <= threshold || flux <= f32::EPSILON {
+            return None;
+        }
+        let min_gap = (MIN_ONSET_GAP * self.sample_rate as f32) as u64;
+        if self
+            .last_onset
+            .is_some_and(|last_onset| frame < last_onset + min_gap)
+        {
+            return None;
+        }
+        self.last_onset = Some(frame);
+
+        let [low, mid, high] = band_flux;
+        let instrument = if low > KICK_DOMINANCE * high && low >= mid {
+            Instrument::Kick
+        } else if high > mid && mid > low {
+            // a snare also rises in the kick band, a hi-hat hardly does
+            Instrument::HiHat
+        } else {
+            Instrument::Snare
+        };
+
+        Some(Onset {
+            frame,
+            strength: flux / self.peak.max(flux),
+            instrument,
+        })
+    }
+}
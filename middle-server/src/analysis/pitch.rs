@@ -0,0 +1,235 @@
+use crate::analysis::level::is_silent;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::{collections::VecDeque, sync::Arc};
+
+/// The hop of the pitch tracker in seconds, which is also its time resolution.
+pub const HOP_SECONDS: f32 = 0.01;
+/// The lowest and highest f0 the tracker looks for in Hz (a bass voice to a soprano whistle).
+const MIN_F0: f32 = 60.0;
+const MAX_F0: f32 = 1000.0;
+/// The length of the difference function window in seconds.
+const WINDOW_SECONDS: f32 = 0.025;
+/// The thresholds of the cumulative mean normalized difference, and the Beta(2, 11.33) prior over
+/// them (mean 0.15), like pYIN.
+const THRESHOLDS: usize = 100;
+const PRIOR_ALPHA: f32 = 2.0;
+const PRIOR_BETA: f32 = 11.33;
+
+/// The pitch of a frame on the stream timeline.
+#[derive(Debug, Clone, Copy)]
+pub struct PitchFrame {
+    /// The frame at the center of the analyzed samples, counted from the start of the stream.
+    pub frame: u64,
+    /// The most likely f0 in Hz, `None` if no period stands out at all.
+    pub f0: Option<f32>,
+    /// The probability that the frame is voiced, from 0.0 to 1.0.
+    pub voicing: f32,
+}
+
+/// Tracks the predominant f0 of an interleaved stream as it arrives (YIN with the
+/// probabilistic thresholds of pYIN, without the HMM).
+pub struct PitchTracker {
+    channels: usize,
+    sample_rate: u32,
+    hop_frames: usize,
+    /// The length of the difference function window and the longest period in samples.
+    window: usize,
+    max_period: usize,
+    min_period: usize,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    /// The weight of each threshold, summing to 1.
+    prior: Vec<f32>,
+    /// The last `window + max_period` mono samples.
+    buffer: VecDeque<f32>,
+    pending_frames: usize,
+    /// The frame after the last sample in `buffer`.
+    end_frame: u64,
+}
+
+impl PitchTracker {
+    /// A tracker for a stream that starts at `start_frame`.
+    pub fn new(channels: usize, sample_rate: u32, start_frame: u64) -> Self {
+        let window = (WINDOW_SECONDS * sample_rate as f32).round() as usize;
+        let max_period = (sample_rate as f32 / MIN_F0).ceil() as usize;
+        let min_period = ((sample_rate as f32 / MAX_F0).floor() as usize).max(2);
+        let fft_length = (2 * window + max_period).next_power_of_two();
+        let mut planner = FftPlanner::<f32>::new();
+
+        // the prior at the center of each threshold bin
+        let mut prior: Vec<f32> = (0..THRESHOLDS)
+            .map(|index| {
+                let x = (index as f32 + 0.5) / THRESHOLDS as f32;
+                x.powf(PRIOR_ALPHA - 1.0) * (1.0 - x).powf(PRIOR_BETA - 1.0)
+            })
+            .collect();
+        let total = prior.iter().sum::<f32>();
+        prior.iter_mut().for_each(|weight| *weight /= total);
+
+        PitchTracker {
+            channels,
+            sample_rate,
+            hop_frames: ((HOP_SECONDS * sample_rate as f32).round() as usize).max(1),
+            window,
+            max_period,
+            min_period,
+            fft: planner.plan_fft_forward(fft_length),
+            ifft: planner.plan_fft_inverse(fft_length),
+            prior,
+            buffer: VecDeque::new(),
+            pending_frames: 0,
+            end_frame: start_frame,
+        }
+    }
+
+    /// The frames between two pitch frames.
+    pub fn hop_frames(&self) -> usize {
+        self.hop_frames
+    }
+
+    /// Restart the tracking at `start_frame`, e.g. after a gap in the stream.
+    pub fn reset(&mut self, start_frame: u64) {
+        *self = PitchTracker::new(self.channels, self.sample_rate, start_frame);
+    }
+
+    /// Track the pitch in interleaved samples that follow the previous ones.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<PitchFrame> {
+        let mut frames = Vec::new();
+        if self.channels == 0 {
+            return frames;
+        }
+        let length = self.window + self.max_period;
+        for frame in samples.chunks_exact(self.channels) {
+            if self.buffer.len() == length {
+                self.buffer.pop_front();
+            }
+            self.buffer
+                .push_back(frame.iter().sum::<f32>() / self.channels as f32);
+            self.end_frame += 1;
+            self.pending_frames += 1;
+            if self.pending_frames >= self.hop_frames && self.buffer.len() == length {
+                self.pending_frames = 0;
+                frames.push(self.hop());
+            }
+        }
+        frames
+    }
+
+    fn hop(&mut self) -> PitchFrame {
+        let frame = self.end_frame - (self.buffer.len() / 2) as u64;
+        let samples: Vec<f32> = self.buffer.iter().copied().collect();
+        if is_silent(&samples) {
+            return PitchFrame {
+                frame,
+                f0: None,
+                voicing: 0.0,
+            };
+        }
+        let difference = self.cumulative_mean_normalized_difference(&samples);
+
+        // the local minima (period, value) of the normalized difference
+        let minima: Vec<(usize, f32)> = (self.min_period..self.max_period)
+            .filter(|&period| {
+                difference[period] < difference[period - 1]
+                    && difference[period] <= difference[period + 1]
+            })
+            .map(|period| (period, difference[period]))
+            .collect();
+
+        // each threshold picks the first minimum below it, and the periods collect the prior
+        // of the thresholds that picked them
+        let mut periods: Vec<(usize, f32)> = Vec::new();
+        for (index, weight) in self.prior.iter().enumerate() {
+            let threshold = (index + 1) as f32 / THRESHOLDS as f32;
+            let Some(&(period, _)) = minima.iter().find(|(_, value)| *value < threshold) else {
+                continue;
+            };
+            match periods.iter_mut().find(|(other, _)| *other == period) {
+                Some((_, sum)) => *sum += weight,
+                None => periods.push((period, *weight)),
+            }
+        }
+        let voicing = periods.iter().map(|(_, weight)| weight).sum::<f32>();
+        let f0 = periods
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|&(period, _)| period)
+            .or_else(|| {
+                // the deepest minimum is the best guess of an unvoiced frame
+                minima
+                    .iter()
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|&(period, _)| period)
+            })
+            .map(|period| self.sample_rate as f32 / refine(&difference, period));
+
+        PitchFrame {
+            frame,
+            f0,
+            voicing: voicing.min(1.0),
+        }
+    }
+
+    // d'(tau) of YIN for tau in 0..=max_period, from the autocorrelation (computed with FFTs)
+    fn cumulative_mean_normalized_difference(&self, samples: &[f32]) -> Vec<f32> {
+        let length = self.fft.len();
+        let mut signal: Vec<Complex<f32>> = samples
+            .iter()
+            .map(|&sample| Complex::new(sample, 0.0))
+            .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+            .take(length)
+            .collect();
+        let mut kernel: Vec<Complex<f32>> = samples[..self.window]
+            .iter()
+            .map(|&sample| Complex::new(sample, 0.0))
+            .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+            .take(length)
+            .collect();
+        self.fft.process(&mut signal);
+        self.fft.process(&mut kernel);
+        let mut correlation: Vec<Complex<f32>> = signal
+            .iter()
+            .zip(&kernel)
+            .map(|(signal, kernel)| *signal * kernel.conj())
+            .collect();
+        self.ifft.process(&mut correlation);
+
+        // the energy of the window starting at each lag
+        let mut energy = vec![0.0f32; self.max_period + 2];
+        energy[0] = samples[..self.window].iter().map(|x| x * x).sum();
+        for tau in 1..energy.len() {
+            let leaving = samples[tau - 1];
+            let entering = samples.get(tau - 1 + self.window).copied().unwrap_or(0.0);
+            energy[tau] = energy[tau - 1] - leaving * leaving + entering * entering;
+        }
+
+        let mut normalized = vec![1.0f32; self.max_period + 2];
+        let mut sum = 0.0;
+        for tau in 1..normalized.len() {
+            let cross = correlation[tau].re / length as f32;
+            let difference = (energy[0] + energy[tau] - 2.0 * cross).max(0.0);
+            sum += difference;
+            normalized[tau] = if sum > 0.0 {
+                difference * tau as f32 / sum
+            } else {
+                1.0
+            };
+        }
+        normalized
+    }
+}
+
+// the period of the minimum at `period`, refined with a parabola through its neighbours
+fn refine(difference: &[f32], period: usize) -> f32 {
+    let (left, center, right) = (
+        difference[period - 1],
+        difference[period],
+        difference[period + 1],
+    );
+    let denominator = left - 2.0 * center + right;
+    if denominator.abs() <= f32::EPSILON {
+        period as f32
+    } else {
+        period as f32 + 0.5 * (left - right) / denominator
+    }
+}
@@ -0,0 +1,328 @@
+use crate::analysis::stft::{HOP_LENGTH, N_FFT, Stft};
+use serde::Serialize;
+
+/// The length of the blocks the features are computed on, in seconds.
+const BLOCK_SECONDS: f32 = 0.5;
+/// The number of blocks compared on each side of a boundary (4 s).
+const CONTEXT_BLOCKS: usize = 8;
+/// A boundary has the highest novelty of this many blocks on each side.
+const PEAK_RADIUS: usize = 2;
+/// The shortest section in blocks (8 s).
+const MIN_SECTION_BLOCKS: usize = 16;
+/// The novelty of a boundary is at least this (roughly a 5 dB change of the features).
+const MIN_NOVELTY: f32 = 0.5;
+/// A boundary has at least this many times the median novelty.
+const NOVELTY_RATIO: f32 = 2.0;
+/// The level of a silent block in dBFS.
+const FLOOR_DB: f32 = -80.0;
+/// The rise of the spectral centroid in octaves that makes a build-up.
+const BUILD_UP_BRIGHTNESS: f32 = 1.0 / 3.0;
+/// The upper edges of the bands the timbre is described by, in Hz (the last band is open).
+const BAND_EDGES: [f32; 3] = [150.0, 1000.0, 5000.0];
+/// The level change in dB that makes a drop or a breakdown.
+const LEVEL_CHANGE_DB: f32 = 3.0;
+/// The level slope in dB per second that makes a build-up.
+const BUILD_UP_SLOPE_DB: f32 = 0.5;
+
+/// The number of features of a block: the level, the share of each band and the brightness.
+const FEATURES: usize = 1 + BAND_EDGES.len() + 1 + 1;
+
+/// A guess of what kind of section starts at a boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionLabel {
+    /// The first section of the track.
+    Intro,
+    /// The level keeps rising.
+    BuildUp,
+    /// The bass comes in (again) at a high level.
+    Drop,
+    /// The level or the bass falls away.
+    Breakdown,
+    /// The last section of the track, quieter than most of it.
+    Outro,
+    /// Something changes, but it does not look like any of the above.
+    Section,
+}
+
+/// The start of a new section on the stream timeline.
+#[derive(Debug, Clone, Copy)]
+pub struct SectionChange {
+    /// The first frame of the new section, counted from the start of the stream.
+    pub frame: u64,
+    pub label: SectionLabel,
+    /// How much the features change at the boundary, from 0.0 to 1.0.
+    pub confidence: f32,
+}
+
+/// A section of the track, covering the frames `start_frame..end_frame`.
+#[derive(Debug, Clone, Copy)]
+pub struct Section {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    pub label: SectionLabel,
+    pub confidence: f32,
+}
+
+struct Block {
+    frame: u64,
+    /// The level in dBFS.
+    level: f32,
+    features: [f32; FEATURES],
+}
+
+/// Finds section boundaries in a mono stream from the novelty of its level and timbre.
+///
+/// The features of every block are compared between the `CONTEXT_BLOCKS` before and after
+/// each block boundary (a checkerboard kernel on the self-similarity), so a boundary is
+/// reported about 5 s after it happens.
+pub struct Segmenter {
+    sample_rate: u32,
+    block_frames: usize,
+    /// The samples of the block being filled, and its first frame.
+    pending: Vec<f32>,
+    pending_frame: u64,
+    blocks: Vec<Block>,
+    /// `novelty[t]` is the novelty of the boundary before block `t + CONTEXT_BLOCKS`.
+    novelty: Vec<f32>,
+    /// The first block of every section so far.
+    boundaries: Vec<(usize, SectionLabel, f32)>,
+}
+
+impl Segmenter {
+    pub fn new(sample_rate: u32) -> Self {
+        Segmenter {
+            sample_rate,
+            block_frames: (BLOCK_SECONDS * sample_rate as f32) as usize,
+            pending: Vec::new(),
+            pending_frame: 0,
+            blocks: Vec::new(),
+            novelty: Vec::new(),
+            boundaries: vec![(0, SectionLabel::Intro, 1.0)],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Add mono samples starting at `start_frame`, and return the boundaries found so far.
+    ///
+    /// Samples before the end of the previous ones are skipped, so overlapping windows can be
+    /// passed as they are.
+    pub fn process(&mut self, samples: &[f32], start_frame: u64) -> Vec<SectionChange> {
+        let end_frame = self.pending_frame + self.pending.len() as u64;
+        let skip = end_frame.saturating_sub(start_frame) as usize;
+        if skip >= samples.len() {
+            return Vec::new();
+        }
+        // a gap in the stream starts the block over
+        if start_frame > end_frame || self.blocks.is_empty() && self.pending.is_empty() {
+            self.pending.clear();
+            self.pending_frame = start_frame;
+        }
+
+        let mut changes = Vec::new();
+        for &sample in &samples[skip..] {
+            self.pending.push(sample);
+            if self.pending.len() == self.block_frames {
+                let block = self.block();
+                self.pending_frame += self.block_frames as u64;
+                self.pending.clear();
+                self.blocks.push(block);
+                changes.extend(self.detect());
+            }
+        }
+        changes
+    }
+
+    /// Every section so far. The last section ends at the last analyzed block.
+    pub fn sections(&self) -> Vec<Section> {
+        let Some(last) = self.blocks.last() else {
+            return Vec::new();
+        };
+        let end_frame = last.frame + self.block_frames as u64;
+        let mut levels: Vec<f32> = self.blocks.iter().map(|block| block.level).collect();
+        levels.sort_by(f32::total_cmp);
+        let median_level = levels[levels.len() / 2];
+
+        let mut sections: Vec<Section> = self
+            .boundaries
+            .iter()
+            .enumerate()
+            .map(|(index, &(start, label, confidence))| Section {
+                start_frame: self.blocks[start].frame,
+                end_frame: self
+                    .boundaries
+                    .get(index + 1)
+                    .map_or(end_frame, |&(end, _, _)| self.blocks[end].frame),
+                label,
+                confidence,
+            })
+            .collect();
+        // a quiet last section (after at least one boundary) is the outro
+        if let [.., section] = sections.as_mut_slice()
+            && self.boundaries.len() > 1
+        {
+            let start = self.boundaries[self.boundaries.len() - 1].0;
+            if mean_level(&self.blocks[start..]) < median_level {
+                section.label = SectionLabel::Outro;
+            }
+        }
+        sections
+    }
+
+    // the features of the pending block
+    fn block(&self) -> Block {
+        let rms = (self
+            .pending
+            .iter()
+            .map(|sample| sample * sample)
+            .sum::<f32>()
+            / self.pending.len() as f32)
+            .sqrt();
+        let level = (20.0 * rms.max(f32::MIN_POSITIVE).log10()).max(FLOOR_DB);
+
+        let stft = Stft::new(&self.pending, self.sample_rate, N_FFT, HOP_LENGTH);
+        let mut bands = [0.0f32; BAND_EDGES.len() + 1];
+        let mut weighted_frequency = 0.0;
+        for frame in &stft.frames {
+            for (bin, value) in frame.iter().enumerate().skip(1) {
+                let frequency = stft.bin_frequency(bin);
+                let power = value.norm_sqr();
+                let band = BAND_EDGES
+                    .iter()
+                    .position(|&edge| frequency < edge)
+                    .unwrap_or(BAND_EDGES.len());
+                bands[band] += power;
+                weighted_frequency += power * frequency;
+            }
+        }
+        let total = bands.iter().sum::<f32>();
+
+        // a 10 dB change is one unit for every feature
+        let mut features = [0.0; FEATURES];
+        features[0] = level / 10.0;
+        if total > f32::MIN_POSITIVE {
+            for (feature, band) in features[1..=bands.len()].iter_mut().zip(bands) {
+                *feature = (band / total).max(1e-6).log10();
+            }
+            // the spectral centroid in octaves around 1 kHz
+            features[FEATURES - 1] = (weighted_frequency / total / 1000.0).max(1e-3).log2() / 3.0;
+        }
+        Block {
+            frame: self.pending_frame,
+            level,
+            features,
+        }
+    }
+
+    // the novelty of the boundary `CONTEXT_BLOCKS` back, and a boundary if the novelty peaked
+    fn detect(&mut self) -> Option<SectionChange> {
+        if self.blocks.len() < 2 * CONTEXT_BLOCKS {
+            return None;
+        }
+        let boundary = self.blocks.len() - CONTEXT_BLOCKS;
+        let before = mean_features(&self.blocks[boundary - CONTEXT_BLOCKS..boundary]);
+        let after = mean_features(&self.blocks[boundary..]);
+        let distance = before
+            .iter()
+            .zip(after)
+            .map(|(before, after)| (before - after).powi(2))
+            .sum::<f32>()
+            .sqrt();
+        self.novelty.push(distance);
+
+        // the candidate is in the middle of the last novelty values
+        let index = self.novelty.len().checked_sub(PEAK_RADIUS + 1)?;
+        let candidate = index + CONTEXT_BLOCKS;
+        let novelty = self.novelty[index];
+        let neighbours = &self.novelty[index.saturating_sub(PEAK_RADIUS)..];
+        if neighbours.iter().any(|&other| other > novelty) || novelty < MIN_NOVELTY {
+            return None;
+        }
+        let last_boundary = self.boundaries.last().map_or(0, |&(block, _, _)| block);
+        if candidate < last_boundary + MIN_SECTION_BLOCKS {
+            return None;
+        }
+        // how far the novelty stands out of the typical novelty of the track so far
+        let mut sorted = self.novelty.clone();
+        sorted.sort_by(f32::total_cmp);
+        let median = sorted[sorted.len() / 2];
+        if novelty < NOVELTY_RATIO * median {
+            return None;
+        }
+        let confidence = 1.0 - median / novelty;
+
+        let label = self.label(candidate);
+        self.boundaries.push((candidate, label, confidence));
+        Some(SectionChange {
+            frame: self.blocks[candidate].frame,
+            label,
+            confidence,
+        })
+    }
+
+    // guess the kind of the section that starts at block `boundary`
+    fn label(&self, boundary: usize) -> SectionLabel {
+        let before = &self.blocks[boundary - CONTEXT_BLOCKS..boundary];
+        let after = &self.blocks[boundary..(boundary + CONTEXT_BLOCKS).min(self.blocks.len())];
+        let level_change = mean_level(after) - mean_level(before);
+        // the change of the share of the bass band in dB
+        let bass_change = 10.0 * (mean_features(after)[1] - mean_features(before)[1]);
+        // the level slope in dB per second
+        let slope = level_slope(after) / BLOCK_SECONDS;
+        // the change of the spectral centroid in octaves
+        let brightness_change =
+            3.0 * (mean_features(after)[FEATURES - 1] - mean_features(before)[FEATURES - 1]);
+
+        if bass_change >= LEVEL_CHANGE_DB && level_change >= 0.0 {
+            SectionLabel::Drop
+        } else if slope >= BUILD_UP_SLOPE_DB {
+            SectionLabel::BuildUp
+        } else if bass_change <= -LEVEL_CHANGE_DB || level_change <= -LEVEL_CHANGE_DB {
+            SectionLabel::Breakdown
+        } else if brightness_change >= BUILD_UP_BRIGHTNESS {
+            // a riser or a snare roll brightens the mix before the level follows
+            SectionLabel::BuildUp
+        } else if level_change >= LEVEL_CHANGE_DB {
+            SectionLabel::Drop
+        } else {
+            SectionLabel::Section
+        }
+    }
+}
+
+fn mean_features(blocks: &[Block]) -> [f32; FEATURES] {
+    let mut mean = [0.0; FEATURES];
+    for block in blocks {
+        for (sum, feature) in mean.iter_mut().zip(block.features) {
+            *sum += feature / blocks.len() as f32;
+        }
+    }
+    mean
+}
+
+fn mean_level(blocks: &[Block]) -> f32 {
+    blocks.iter().map(|block| block.level).sum::<f32>() / blocks.len().max(1) as f32
+}
+
+// the least-squares slope of the level in dB per block
+fn level_slope(blocks: &[Block]) -> f32 {
+    let count = blocks.len() as f32;
+    let mean_x = (count - 1.0) / 2.0;
+    let mean_y = mean_level(blocks);
+    let (covariance, variance) =
+        blocks
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(covariance, variance), (x, block)| {
+                let dx = x as f32 - mean_x;
+                (covariance + dx * (block.level - mean_y), variance + dx * dx)
+            });
+    if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,123 @@
+use crate::analysis::stft::hann_window;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::{collections::VecDeque, ops::Range, sync::Arc};
+
+/// The band count of a session that does not choose one.
+pub const DEFAULT_BANDS: usize = 32;
+/// The largest band count a session may choose.
+pub const MAX_BANDS: usize = 128;
+/// The update rate (per second) of a session that does not choose one.
+pub const DEFAULT_RATE: f64 = 20.0;
+/// The fastest update rate (per second) a session may choose.
+pub const MAX_RATE: f64 = 60.0;
+/// The slowest update rate (per second) a session may choose.
+pub const MIN_RATE: f64 = 1.0;
+/// The FFT size of the spectrum.
+const N_FFT: usize = 2048;
+/// The lowest edge of the bands in Hz.
+pub const MIN_FREQUENCY: f32 = 20.0;
+/// The highest edge of the bands in Hz (or the Nyquist frequency if it is lower).
+pub const MAX_FREQUENCY: f32 = 20000.0;
+/// The level that maps to 0 in the quantized spectrum; 0 dB maps to 255.
+pub const FLOOR_DB: f32 = -100.0;
+
+/// Log-spaced band energies of an interleaved stream, one spectrum every `hop_frames` frames.
+///
+/// The channels are mixed down to mono. A full-scale sine reads 0 dB in its band.
+pub struct SpectrumAnalyzer {
+    channels: usize,
+    hop_frames: usize,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// The bins of each band.
+    bands: Vec<Range<usize>>,
+    /// The last `N_FFT` mono samples.
+    buffer: VecDeque<f32>,
+    /// The frames since the last spectrum.
+    pending_frames: usize,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(channels: usize, sample_rate: u32, bands: usize, hop_frames: usize) -> Self {
+        SpectrumAnalyzer {
+            channels,
+            hop_frames: hop_frames.max(1),
+            fft: FftPlanner::<f32>::new().plan_fft_forward(N_FFT),
+            window: hann_window(N_FFT),
+            bands: band_bins(sample_rate, bands),
+            buffer: VecDeque::from(vec![0.0; N_FFT]),
+            pending_frames: 0,
+        }
+    }
+
+    /// Analyze interleaved samples, returning the band levels (in dB) of every completed hop.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        let mut spectra = Vec::new();
+        if self.channels == 0 {
+            return spectra;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            self.buffer.pop_front();
+            self.buffer
+                .push_back(frame.iter().sum::<f32>() / self.channels as f32);
+            self.pending_frames += 1;
+            if self.pending_frames == self.hop_frames {
+                self.pending_frames = 0;
+                spectra.push(self.spectrum());
+            }
+        }
+        spectra
+    }
+
+    fn spectrum(&self) -> Vec<f32> {
+        let mut buffer: Vec<Complex<f32>> = self
+            .buffer
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| Complex::new(sample * window, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+
+        // the one-sided energy of a full-scale sine through a Hann window
+        let reference = 3.0 * (N_FFT * N_FFT) as f32 / 32.0;
+        self.bands
+            .iter()
+            .map(|bins| {
+                let energy: f32 = buffer[bins.clone()].iter().map(|bin| bin.norm_sqr()).sum();
+                if energy > 0.0 {
+                    (10.0 * (energy / reference).log10()).max(FLOOR_DB)
+                } else {
+                    FLOOR_DB
+                }
+            })
+            .collect()
+    }
+}
+
+/// Quantize a level in dB to a byte, `FLOOR_DB` (or below) to 0 and 0 dB (or above) to 255.
+pub fn quantize(level: f32) -> u8 {
+    ((level - FLOOR_DB) / -FLOOR_DB * 255.0)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
+
+// the bins of `bands` log-spaced bands; a band narrower than a bin gets its nearest bin
+fn band_bins(sample_rate: u32, bands: usize) -> Vec<Range<usize>> {
+    let bin_width = sample_rate as f32 / N_FFT as f32;
+    let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+    let ratio = (max_frequency / MIN_FREQUENCY).powf(1.0 / bands.max(1) as f32);
+    (0..bands)
+        .map(|band| {
+            let low = MIN_FREQUENCY * ratio.powi(band as i32);
+            let high = low * ratio;
+            let start = (low / bin_width).ceil() as usize;
+            let end = ((high / bin_width).ceil() as usize).min(N_FFT / 2 + 1);
+            if start < end {
+                start..end
+            } else {
+                let center = (((low * high).sqrt() / bin_width).round() as usize).min(N_FFT / 2);
+                center..center + 1
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,117 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+
+/// The FFT size used by the analysis (46 ms at 44.1 kHz).
+pub const N_FFT: usize = 2048;
+/// The hop between two STFT frames (11.6 ms at 44.1 kHz).
+pub const HOP_LENGTH: usize = 512;
+
+/// Periodic Hann window of `length` samples.
+pub fn hann_window(length: usize) -> Vec<f32> {
+    (0..length)
+        .map(|n| {
+            let phase = 2.0 * std::f32::consts::PI * n as f32 / length as f32;
+            0.5 - 0.5 * phase.cos()
+        })
+        .collect()
+}
+
+/// Short-time Fourier transform of a mono signal.
+///
+/// Frames are centered: frame `t` is centered on sample `t * hop_length`,
+/// and the signal is zero-padded at both ends.
+pub struct Stft {
+    /// `frames[t][k]` is bin `k` (0..=n_fft/2) of frame `t`.
+    pub frames: Vec<Vec<Complex<f32>>>,
+    pub n_fft: usize,
+    pub hop_length: usize,
+    pub sample_rate: u32,
+}
+
+impl Stft {
+    pub fn new(samples: &[f32], sample_rate: u32, n_fft: usize, hop_length: usize) -> Self {
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(n_fft);
+        let window = hann_window(n_fft);
+        let frame_count = samples.len() / hop_length + 1;
+        let half = (n_fft / 2) as isize;
+
+        let mut buffer = vec![Complex::new(0.0, 0.0); n_fft];
+        let frames = (0..frame_count)
+            .map(|t| {
+                let offset = (t * hop_length) as isize - half;
+                for (n, value) in buffer.iter_mut().enumerate() {
+                    let index = offset + n as isize;
+                    let sample = if index >= 0 && (index as usize) < samples.len() {
+                        samples[index as usize]
+                    } else {
+                        0.0
+                    };
+                    *value = Complex::new(sample * window[n], 0.0);
+                }
+                fft.process(&mut buffer);
+                buffer[..=n_fft / 2].to_vec()
+            })
+            .collect();
+
+        Stft {
+            frames,
+            n_fft,
+            hop_length,
+            sample_rate,
+        }
+    }
+
+    /// Magnitude spectrogram, `[frame][bin]`.
+    pub fn magnitudes(&self) -> Vec<Vec<f32>> {
+        self.frames
+            .iter()
+            .map(|frame| frame.iter().map(|value| value.norm()).collect())
+            .collect()
+    }
+
+    /// The center frequency of `bin` in Hz.
+    pub fn bin_frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.sample_rate as f32 / self.n_fft as f32
+    }
+
+    /// The number of frames per second.
+    pub fn frame_rate(&self) -> f32 {
+        self.sample_rate as f32 / self.hop_length as f32
+    }
+
+    /// The signal of `length` samples whose STFT is `frames` (weighted overlap-add with the
+    /// Hann window, so `Stft::new(..).inverse(&stft.frames, ..)` gives the signal back).
+    pub fn inverse(&self, frames: &[Vec<Complex<f32>>], length: usize) -> Vec<f32> {
+        let ifft = FftPlanner::<f32>::new().plan_fft_inverse(self.n_fft);
+        let window = hann_window(self.n_fft);
+        let half = (self.n_fft / 2) as isize;
+        let mut signal = vec![0.0f32; length];
+        let mut weight = vec![0.0f32; length];
+
+        let mut buffer = vec![Complex::new(0.0, 0.0); self.n_fft];
+        for (t, frame) in frames.iter().enumerate() {
+            // the negative frequencies are the conjugates of the positive ones
+            for (bin, value) in buffer.iter_mut().enumerate() {
+                *value = if bin <= self.n_fft / 2 {
+                    frame[bin]
+                } else {
+                    frame[self.n_fft - bin].conj()
+                };
+            }
+            ifft.process(&mut buffer);
+            let offset = (t * self.hop_length) as isize - half;
+            for (n, value) in buffer.iter().enumerate() {
+                let index = offset + n as isize;
+                if index >= 0 && (index as usize) < length {
+                    signal[index as usize] += value.re / self.n_fft as f32 * window[n];
+                    weight[index as usize] += window[n] * window[n];
+                }
+            }
+        }
+        for (sample, weight) in signal.iter_mut().zip(weight) {
+            if weight > f32::EPSILON {
+                *sample /= weight;
+            }
+        }
+        signal
+    }
+}
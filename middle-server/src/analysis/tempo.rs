@@ -0,0 +1,160 @@
+use crate::analysis::stft::{HOP_LENGTH, N_FFT, Stft};
+
+/// The slowest tempo the estimator reports.
+pub const MIN_BPM: f32 = 30.0;
+/// The fastest tempo the estimator reports.
+pub const MAX_BPM: f32 = 300.0;
+/// The center of the tempo prior, like `start_bpm` of `librosa.beat.beat_track`.
+pub const START_BPM: f32 = 120.0;
+/// The width of the tempo prior in octaves.
+const PRIOR_OCTAVES: f32 = 1.0;
+/// The dynamic range of the log spectrogram in dB.
+const TOP_DB: f32 = 80.0;
+
+/// Onset strength envelope of a mono signal.
+///
+/// The envelope is the half-wave rectified spectral flux of the log-power spectrogram,
+/// one value per STFT frame (`sample_rate / HOP_LENGTH` values per second).
+pub fn onset_strength(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let stft = Stft::new(samples, sample_rate, N_FFT, HOP_LENGTH);
+    onset_strength_from_magnitudes(&stft.magnitudes())
+}
+
+/// Onset strength envelope of a magnitude spectrogram (`[frame][bin]`).
+pub fn onset_strength_from_magnitudes(magnitudes: &[Vec<f32>]) -> Vec<f32> {
+    // log-power spectrogram, clipped to TOP_DB below its maximum
+    let mut decibels: Vec<Vec<f32>> = magnitudes
+        .iter()
+        .map(|frame| {
+            frame
+                .iter()
+                .map(|magnitude| 10.0 * (magnitude * magnitude).max(1e-10).log10())
+                .collect()
+        })
+        .collect();
+    let peak = decibels
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f32::NEG_INFINITY, f32::max);
+    for value in decibels.iter_mut().flatten() {
+        *value = value.max(peak - TOP_DB);
+    }
+
+    let mut envelope = vec![0.0; decibels.len()];
+    for t in 1..decibels.len() {
+        let (previous, current) = (&decibels[t - 1], &decibels[t]);
+        let flux: f32 = current
+            .iter()
+            .zip(previous)
+            .map(|(current, previous)| (current - previous).max(0.0))
+            .sum();
+        envelope[t] = flux / current.len().max(1) as f32;
+    }
+    envelope
+}
+
+/// Estimate the tempo in BPM from an onset strength envelope sampled at `frame_rate` Hz.
+///
+/// The autocorrelation of the envelope (a global tempogram) is weighted with a
+/// log-normal prior around `START_BPM`, and the best lag is refined by parabolic interpolation.
+pub fn estimate_tempo(envelope: &[f32], frame_rate: f32) -> Option<f32> {
+    let autocorrelation = tempogram(envelope, frame_rate)?;
+    let min_lag = min_lag(frame_rate);
+
+    let scores: Vec<f32> = autocorrelation
+        .iter()
+        .enumerate()
+        .map(|(lag, value)| {
+            if lag < min_lag {
+                return 0.0;
+            }
+            value.max(0.0) * tempo_prior(lag_to_bpm(lag as f32, frame_rate))
+        })
+        .collect();
+    let (best_lag, best_score) = scores
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if best_score <= 0.0 {
+        return None;
+    }
+
+    // parabolic interpolation between the neighbouring lags
+    let mut lag = best_lag as f32;
+    if best_lag > min_lag && best_lag + 1 < scores.len() {
+        let (left, right) = (scores[best_lag - 1], scores[best_lag + 1]);
+        let denominator = left - 2.0 * best_score + right;
+        if denominator < 0.0 {
+            lag += 0.5 * (left - right) / denominator;
+        }
+    }
+    Some(lag_to_bpm(lag, frame_rate))
+}
+
+/// Normalized autocorrelation of the mean-removed envelope for the lags `0..=max_lag`.
+///
+/// Returns `None` if the envelope is too short or flat.
+pub fn tempogram(envelope: &[f32], frame_rate: f32) -> Option<Vec<f32>> {
+    let max_lag =
+        ((60.0 * frame_rate / MIN_BPM).ceil() as usize).min(envelope.len().checked_sub(1)?);
+    if max_lag < min_lag(frame_rate) {
+        return None;
+    }
+
+    let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+    let centered: Vec<f32> = envelope.iter().map(|value| value - mean).collect();
+    let energy: f32 = centered.iter().map(|value| value * value).sum();
+    if energy <= f32::EPSILON {
+        return None;
+    }
+
+    Some(
+        (0..=max_lag)
+            .map(|lag| {
+                let sum: f32 = centered[lag..]
+                    .iter()
+                    .zip(&centered)
+                    .map(|(a, b)| a * b)
+                    .sum();
+                // unbiased, so long lags are not penalized by the window length
+                sum / energy * centered.len() as f32 / (centered.len() - lag) as f32
+            })
+            .collect(),
+    )
+}
+
+/// How periodic an onset strength envelope is at `bpm`, from 0.0 (not at all) to 1.0.
+///
+/// This is the normalized autocorrelation at the beat period.
+pub fn periodicity(envelope: &[f32], frame_rate: f32, bpm: f32) -> f32 {
+    let Some(autocorrelation) = tempogram(envelope, frame_rate) else {
+        return 0.0;
+    };
+    if bpm <= 0.0 {
+        return 0.0;
+    }
+    let lag = 60.0 * frame_rate / bpm;
+    let index = lag.floor() as usize;
+    if index + 1 >= autocorrelation.len() {
+        return 0.0;
+    }
+    let fraction = lag - index as f32;
+    let value = autocorrelation[index] * (1.0 - fraction) + autocorrelation[index + 1] * fraction;
+    value.clamp(0.0, 1.0)
+}
+
+fn min_lag(frame_rate: f32) -> usize {
+    ((60.0 * frame_rate / MAX_BPM).floor() as usize).max(1)
+}
+
+fn lag_to_bpm(lag: f32, frame_rate: f32) -> f32 {
+    60.0 * frame_rate / lag
+}
+
+// log-normal weight centered on START_BPM
+fn tempo_prior(bpm: f32) -> f32 {
+    let octaves = (bpm / START_BPM).log2() / PRIOR_OCTAVES;
+    (-0.5 * octaves * octaves).exp()
+}
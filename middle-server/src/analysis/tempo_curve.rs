@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+
+/// The local tempo is the median of this many recent beat intervals.
+const SMOOTHING_INTERVALS: usize = 3;
+/// An interval within this fraction of a multiple of the local period skips that many beats.
+const MULTIPLE_TOLERANCE: f64 = 0.2;
+/// An interval longer than this many local periods breaks the curve (e.g. a break in the music).
+const MAX_INTERVAL_RATIO: f64 = 4.5;
+
+/// The local tempo at a beat.
+#[derive(Debug, Clone, Copy)]
+pub struct TempoPoint {
+    /// The time of the beat in seconds from the start of the stream.
+    pub time: f64,
+    pub bpm: f64,
+}
+
+/// Follows the tempo from beat to beat, so a drifting tempo shows up as a curve.
+///
+/// The local tempo is the median of the last few beat intervals, so a single early or late beat
+/// does not bend the curve. An interval that spans several beats (a missed beat) is divided.
+#[derive(Debug, Default)]
+pub struct TempoCurve {
+    last_beat: Option<f64>,
+    intervals: VecDeque<f64>,
+}
+
+impl TempoCurve {
+    /// Add the next beat, and return the local tempo at it (`None` at the start of the curve).
+    pub fn push(&mut self, time: f64) -> Option<TempoPoint> {
+        let last_beat = self.last_beat.replace(time)?;
+        let mut interval = time - last_beat;
+        if interval <= 0.0 {
+            return None;
+        }
+        if let Some(period) = median(&self.intervals) {
+            let ratio = interval / period;
+            if ratio > MAX_INTERVAL_RATIO {
+                // start a new curve at this beat
+                self.intervals.clear();
+                return None;
+            }
+            let beats = ratio.round().max(1.0);
+            if beats > 1.0 && (ratio / beats - 1.0).abs() <= MULTIPLE_TOLERANCE {
+                interval /= beats;
+            }
+        }
+
+        if self.intervals.len() == SMOOTHING_INTERVALS {
+            self.intervals.pop_front();
+        }
+        self.intervals.push_back(interval);
+        median(&self.intervals).map(|period| TempoPoint {
+            time,
+            bpm: 60.0 / period,
+        })
+    }
+}
+
+fn median(values: &VecDeque<f64>) -> Option<f64> {
+    let mut sorted: Vec<f64> = values.iter().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    sorted.get(sorted.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the local tempo at each of `times` after the first
+    fn curve(times: &[f64]) -> Vec<Option<f64>> {
+        let mut tempo_curve = TempoCurve::default();
+        times
+            .iter()
+            .map(|time| tempo_curve.push(*time).map(|point| point.bpm))
+            .collect()
+    }
+
+    #[test]
+    fn steady_beats_give_a_flat_curve() {
+        let times: Vec<f64> = (0..8).map(|beat| beat as f64 * 0.5).collect();
+        let bpms = curve(&times);
+        assert_eq!(bpms[0], None);
+        for bpm in &bpms[1..] {
+            assert!((bpm.unwrap() - 120.0).abs() < 1e-9, "{bpm:?}");
+        }
+    }
+
+    #[test]
+    fn missed_beat_and_outlier_do_not_bend_the_curve() {
+        // the beat at 2.0 s is missing and the one at 3.0 s is 50 ms late
+        let times = [0.0, 0.5, 1.0, 1.5, 2.5, 3.05, 3.5, 4.0];
+        for bpm in curve(&times).into_iter().skip(1) {
+            assert!((bpm.unwrap() - 120.0).abs() < 1e-9, "{bpm:?}");
+        }
+    }
+
+    #[test]
+    fn drifting_tempo_is_followed() {
+        // the beats speed up from 100 to about 140 BPM
+        let mut times = vec![0.0];
+        for beat in 0..40 {
+            let bpm = 100.0 + beat as f64;
+            times.push(times[beat] + 60.0 / bpm);
+        }
+        let bpms = curve(&times);
+        let last = bpms.last().unwrap().unwrap();
+        assert!((last - 138.0).abs() < 1.5, "{last}");
+        assert!(bpms[5].unwrap() < 106.0, "{:?}", bpms[5]);
+    }
+
+    #[test]
+    fn long_break_restarts_the_curve() {
+        let bpms = curve(&[0.0, 0.5, 1.0, 1.5, 10.0, 10.4, 10.8]);
+        assert_eq!(bpms[4], None);
+        assert!((bpms[5].unwrap() - 150.0).abs() < 1e-9);
+        assert!((bpms[6].unwrap() - 150.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+/// The number of windows the tracker remembers.
+const HISTORY_LENGTH: usize = 8;
+/// Two tempi within this many octaves (about 4 %) are the same tempo.
+const TOLERANCE_OCTAVES: f64 = 0.06;
+/// Windows that are less periodic than this have no tempo (e.g. noise or ambient pads).
+const MIN_STRENGTH: f64 = 0.15;
+/// The tempo is reported once the confidence reaches this value.
+const MIN_CONFIDENCE: f64 = 0.25;
+/// The ratios that octave errors (and the common 3:2 confusion) introduce.
+const OCTAVE_RATIOS: [f64; 5] = [1.0, 2.0, 0.5, 1.5, 2.0 / 3.0];
+
+/// The tempo estimate of one window.
+#[derive(Debug, Clone, Copy)]
+pub struct TempoObservation {
+    pub bpm: f64,
+    /// How periodic the window is, from 0.0 (not at all) to 1.0.
+    pub strength: f64,
+}
+
+/// The tempo reported by the tracker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackedTempo {
+    /// The tempo in BPM, `None` if there is no steady tempo (e.g. silence or ambient passages).
+    pub bpm: Option<f64>,
+    /// How much the recent windows with a tempo agree on it, from 0.0 to 1.0.
+    pub confidence: f64,
+}
+
+/// Fuses the per-window tempo estimates of a stream into a stable tempo.
+///
+/// Each estimate is folded onto the current tempo to undo octave errors,
+/// and the tempo is the weighted median of the recent estimates.
+/// A window without a tempo reports no tempo right away, but the tempo is only forgotten
+/// once no remembered window has one.
+#[derive(Debug, Default)]
+pub struct TempoTracker {
+    /// The recent observations, `None` for windows without a tempo.
+    history: VecDeque<Option<TempoObservation>>,
+    tempo: Option<f64>,
+}
+
+impl TempoTracker {
+    /// Add the estimate of the next window (`None` if the window has no tempo).
+    pub fn update(&mut self, observation: Option<TempoObservation>) -> TrackedTempo {
+        let observation = observation
+            .filter(|observation| observation.bpm > 0.0 && observation.strength >= MIN_STRENGTH)
+            .map(|observation| TempoObservation {
+                bpm: match self.tempo {
+                    Some(tempo) => fold_octave(observation.bpm, tempo),
+                    None => observation.bpm,
+                },
+                strength: observation.strength.min(1.0),
+            });
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(observation);
+
+        let median = self.weighted_median();
+        if median.is_none() {
+            self.tempo = None;
+        }
+        let (Some(median), Some(_)) = (median, observation) else {
+            return TrackedTempo {
+                bpm: None,
+                confidence: 0.0,
+            };
+        };
+
+        // average the estimates that agree with the median for a finer tempo
+        let (mut weight, mut log_sum, mut count) = (0.0, 0.0, 0);
+        for observation in self.history.iter().flatten() {
+            count += 1;
+            if same_tempo(observation.bpm, median) {
+                weight += observation.strength;
+                log_sum += observation.strength * observation.bpm.log2();
+            }
+        }
+        let tempo = (log_sum / weight).exp2();
+        let confidence = weight / count as f64;
+        self.tempo = Some(tempo);
+
+        TrackedTempo {
+            bpm: (confidence >= MIN_CONFIDENCE).then_some(tempo),
+            confidence,
+        }
+    }
+
+    // the median of the remembered tempi, weighted by their strength
+    fn weighted_median(&self) -> Option<f64> {
+        let mut observations: Vec<TempoObservation> =
+            self.history.iter().flatten().copied().collect();
+        if observations.is_empty() {
+            return None;
+        }
+        observations.sort_by(|a, b| a.bpm.total_cmp(&b.bpm));
+        let half = observations.iter().map(|o| o.strength).sum::<f64>() / 2.0;
+        let mut cumulative = 0.0;
+        observations
+            .iter()
+            .find(|observation| {
+                cumulative += observation.strength;
+                cumulative >= half
+            })
+            .map(|observation| observation.bpm)
+    }
+}
+
+// the multiple of `bpm` that is closest to `reference`, if it is the same tempo
+fn fold_octave(bpm: f64, reference: f64) -> f64 {
+    OCTAVE_RATIOS
+        .iter()
+        .map(|ratio| bpm * ratio)
+        .find(|candidate| same_tempo(*candidate, reference))
+        .unwrap_or(bpm)
+}
+
+fn same_tempo(a: f64, b: f64) -> bool {
+    (a / b).log2().abs() <= TOLERANCE_OCTAVES
+}
@@ -1,4 +1,7 @@
 pub mod client_to_server;
+pub mod detector;
+pub mod executor;
 pub mod pcm;
 pub mod server_to_client;
+pub mod stream_detectors;
 pub mod window;
@@ -1,8 +1,15 @@
 use crate::{
     errors::handler::HandlerError,
-    models::ws::{WebSocketClientReader, WebSocketServerWriter},
+    models::{
+        options::RwLockAnalysisOptions,
+        ws::{WebSocketClientReader, WebSocketServerWriter},
+    },
 };
 use axum::extract::ws::Message;
+use common::{
+    errors::protocol::ProtocolError,
+    protocol::{ControlFrame, ControlMessage},
+};
 use futures_util::{SinkExt, StreamExt};
 use tokio_tungstenite::tungstenite;
 
@@ -10,21 +17,39 @@ use tokio_tungstenite::tungstenite;
 pub async fn handle_client_to_server(
     mut client_reader: WebSocketClientReader,
     mut server_writer: WebSocketServerWriter,
+    shared_analysis_options: RwLockAnalysisOptions,
 ) -> Result<(), HandlerError> {
     while let Some(Ok(message)) = client_reader.next().await {
         match message {
             Message::Text(text) => {
                 tracing::info!("Received text from client: {:?}", text);
-                // send only "open" or "accept" messages to server
-                //* step1: receive open message from client and send to server *//
-                //* step4: receive accept message from client and send to server *//
-                if text == "open" || text == "accept" {
-                    tracing::info!("Forwarding message from client to server: {}", text);
-                    server_writer
-                        .send(tungstenite::Message::Text(text.to_string().into()))
-                        .await
-                        .map_err(HandlerError::TokioTungsteniteError)?;
+                // reject unknown messages and unsupported protocol versions
+                let frame = ControlFrame::from_json(text.as_str())?;
+                match frame.message {
+                    // the stream header is only sent by the server
+                    ControlMessage::StreamHeader(_) => {
+                        return Err(ProtocolError::UnexpectedMessageError(
+                            "stream_header is only sent by the server".into(),
+                        )
+                        .into());
+                    }
+                    // keep the analysis options of the session
+                    ControlMessage::Open {
+                        analysis: Some(analysis),
+                        ..
+                    } => {
+                        *shared_analysis_options.write().await = analysis;
+                    }
+                    _ => {}
                 }
+                //* step0: receive hello message from client and send to server *//
+                //* step1: receive open message (with the selected track) from client and send to server *//
+                //* step4: receive accept message from client and send to server *//
+                tracing::info!("Forwarding message from client to server: {}", text);
+                server_writer
+                    .send(tungstenite::Message::Text(text.to_string().into()))
+                    .await
+                    .map_err(HandlerError::from)?;
             }
             Message::Close(close) => {
                 tracing::info!("Client disconnected: {:?}", close);
@@ -43,11 +68,11 @@ pub async fn handle_client_to_server(
                 server_writer
                     .send(tungstenite::Message::Close(tungstenite_close))
                     .await
-                    .map_err(HandlerError::TokioTungsteniteError)?;
+                    .map_err(HandlerError::from)?;
             }
             _ => {
                 tracing::error!("Received unsupported message type from client");
-                return Err(HandlerError::UnexpectedMessageTypeError);
+                return Err(ProtocolError::UnexpectedMessageTypeError.into());
             }
         };
     }
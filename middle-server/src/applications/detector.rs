@@ -0,0 +1,61 @@
+use crate::{errors::handler::HandlerError, models::config::Config};
+use common::protocol::TempoDetectorKind;
+
+pub mod fixed;
+#[cfg(feature = "python")]
+pub mod librosa;
+pub mod native;
+#[cfg(feature = "python")]
+pub mod python;
+
+/// The tempo of one analysis window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoEstimate {
+    /// The tempo in BPM, 0.0 if the window has no tempo (e.g. silence).
+    pub bpm: f64,
+    /// The beats in seconds from the start of the window, in ascending order.
+    pub beats: Vec<f64>,
+}
+
+/// Estimates the tempo and the beats of a window of mono samples normalized to [-1.0, 1.0].
+///
+/// Detectors run on the analysis executor thread, so they may block,
+/// and they live for the whole session, so they may keep state between windows.
+pub trait TempoDetector: Send {
+    fn detect(&mut self, samples: &[f32], sample_rate: u32) -> Result<TempoEstimate, HandlerError>;
+}
+
+/// Create the tempo detector `kind` of a session.
+///
+/// A fixed tempo in the configuration replaces every detector.
+/// This may block (e.g. to import a Python module), so call it outside the async runtime.
+pub fn create_tempo_detector(
+    kind: TempoDetectorKind,
+    config: &Config,
+) -> Result<Box<dyn TempoDetector>, HandlerError> {
+    if let Some(bpm) = config.fixed_tempo {
+        return Ok(Box::new(fixed::FixedTempoDetector { bpm }));
+    }
+    config
+        .check_tempo_detector(kind)
+        .map_err(HandlerError::TempoDetectorUnavailableError)?;
+
+    match kind {
+        TempoDetectorKind::Native => Ok(Box::new(native::NativeTempoDetector)),
+        #[cfg(feature = "python")]
+        TempoDetectorKind::Librosa => Ok(Box::new(librosa::LibrosaTempoDetector::new()?)),
+        #[cfg(feature = "python")]
+        TempoDetectorKind::Python => {
+            // checked by check_tempo_detector
+            let callable = config
+                .tempo_detector_callable
+                .as_deref()
+                .unwrap_or_default();
+            Ok(Box::new(python::PythonTempoDetector::new(callable)?))
+        }
+        #[cfg(not(feature = "python"))]
+        TempoDetectorKind::Librosa | TempoDetectorKind::Python => {
+            unreachable!("rejected by check_tempo_detector")
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::{
+    applications::detector::{TempoDetector, TempoEstimate},
+    errors::handler::HandlerError,
+};
+
+/// A detector that reports the same tempo for every window, with a beat on every period
+/// from the start of the window.
+///
+/// Selected with `FIXED_TEMPO`, e.g. to check a client against a deterministic beat grid.
+pub struct FixedTempoDetector {
+    pub bpm: f64,
+}
+
+impl TempoDetector for FixedTempoDetector {
+    fn detect(&mut self, samples: &[f32], sample_rate: u32) -> Result<TempoEstimate, HandlerError> {
+        let duration = samples.len() as f64 / sample_rate as f64;
+        let period = 60.0 / self.bpm;
+        let beats = (0..)
+            .map(|index| index as f64 * period)
+            .take_while(|time| *time < duration)
+            .collect();
+        Ok(TempoEstimate {
+            bpm: self.bpm,
+            beats,
+        })
+    }
+}
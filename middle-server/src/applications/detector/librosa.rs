@@ -0,0 +1,48 @@
+use crate::{
+    applications::detector::{TempoDetector, TempoEstimate},
+    errors::handler::HandlerError,
+};
+use numpy::IntoPyArray;
+use pyo3::{
+    Py, PyAny, PyResult, Python,
+    types::{PyAnyMethods, PyDict},
+};
+
+/// `librosa.beat.beat_track` through pyo3.
+pub struct LibrosaTempoDetector {
+    beat_track: Py<PyAny>,
+}
+
+impl LibrosaTempoDetector {
+    /// Import librosa once for the whole session.
+    pub fn new() -> Result<Self, HandlerError> {
+        let beat_track = Python::with_gil(|py| -> PyResult<Py<PyAny>> {
+            // [python code]
+            // import librosa
+            let librosa = py.import("librosa")?;
+            Ok(librosa.getattr("beat")?.getattr("beat_track")?.unbind())
+        })?;
+        Ok(LibrosaTempoDetector { beat_track })
+    }
+}
+
+impl TempoDetector for LibrosaTempoDetector {
+    fn detect(&mut self, samples: &[f32], sample_rate: u32) -> Result<TempoEstimate, HandlerError> {
+        let (bpm, beats) = Python::with_gil(|py| -> PyResult<(f64, Vec<f64>)> {
+            // [python code]
+            // kwargs = {"y": samples, "sr": sample_rate, "units": "time"}
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("y", samples.to_vec().into_pyarray(py))?;
+            kwargs.set_item("sr", sample_rate as f64)?;
+            kwargs.set_item("units", "time")?;
+
+            // [python code]
+            // tempo, beats = librosa.beat.beat_track(kwargs)
+            self.beat_track
+                .bind(py)
+                .call((), Some(&kwargs))?
+                .extract::<(f64, Vec<f64>)>()
+        })?;
+        Ok(TempoEstimate { bpm, beats })
+    }
+}
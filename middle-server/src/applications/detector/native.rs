@@ -0,0 +1,34 @@
+use crate::{
+    analysis::{
+        beat::track_beats,
+        stft::HOP_LENGTH,
+        tempo::{estimate_tempo, onset_strength},
+    },
+    applications::detector::{TempoDetector, TempoEstimate},
+    errors::handler::HandlerError,
+};
+
+/// The pure-Rust estimator (onset strength envelope + autocorrelation tempogram),
+/// with a dynamic programming beat tracker.
+pub struct NativeTempoDetector;
+
+impl TempoDetector for NativeTempoDetector {
+    fn detect(&mut self, samples: &[f32], sample_rate: u32) -> Result<TempoEstimate, HandlerError> {
+        let envelope = onset_strength(samples, sample_rate);
+        let frame_rate = sample_rate as f32 / HOP_LENGTH as f32;
+        let Some(bpm) = estimate_tempo(&envelope, frame_rate) else {
+            return Ok(TempoEstimate {
+                bpm: 0.0,
+                beats: Vec::new(),
+            });
+        };
+        let beats = track_beats(&envelope, frame_rate, bpm)
+            .into_iter()
+            .map(|frame| frame as f64 / frame_rate as f64)
+            .collect();
+        Ok(TempoEstimate {
+            bpm: bpm as f64,
+            beats,
+        })
+    }
+}
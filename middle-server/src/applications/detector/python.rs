@@ -0,0 +1,46 @@
+use crate::{
+    applications::detector::{TempoDetector, TempoEstimate},
+    errors::handler::HandlerError,
+};
+use numpy::IntoPyArray;
+use pyo3::{Py, PyAny, PyResult, Python, types::PyAnyMethods};
+
+/// A user-supplied Python callable.
+///
+/// The callable is given as `module:function` and is called as
+/// `function(samples: numpy.ndarray[float32], sample_rate: float)`.
+/// It returns the tempo in BPM, or a tuple of the tempo and the beat times in seconds.
+pub struct PythonTempoDetector {
+    function: Py<PyAny>,
+}
+
+impl PythonTempoDetector {
+    /// Import `module` and look up `function` once for the whole session.
+    pub fn new(callable: &str) -> Result<Self, HandlerError> {
+        let (module, function) = callable.split_once(':').ok_or_else(|| {
+            HandlerError::TempoDetectorUnavailableError(format!(
+                "expected module:function, got {callable}"
+            ))
+        })?;
+        let function = Python::with_gil(|py| -> PyResult<Py<PyAny>> {
+            Ok(py.import(module)?.getattr(function)?.unbind())
+        })?;
+        Ok(PythonTempoDetector { function })
+    }
+}
+
+impl TempoDetector for PythonTempoDetector {
+    fn detect(&mut self, samples: &[f32], sample_rate: u32) -> Result<TempoEstimate, HandlerError> {
+        let (bpm, beats) = Python::with_gil(|py| -> PyResult<(f64, Vec<f64>)> {
+            let result = self
+                .function
+                .bind(py)
+                .call1((samples.to_vec().into_pyarray(py), sample_rate as f64))?;
+            match result.extract::<(f64, Vec<f64>)>() {
+                Ok(tempo_and_beats) => Ok(tempo_and_beats),
+                Err(_) => Ok((result.extract::<f64>()?, Vec::new())),
+            }
+        })?;
+        Ok(TempoEstimate { bpm, beats })
+    }
+}
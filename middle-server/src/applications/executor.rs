@@ -0,0 +1,339 @@
+use crate::{
+    analysis::{
+        beat::low_frequency_energy,
+        channel::{deinterleave, select, stereo_image},
+        chord::{ChordTracker, recognize_chords},
+        chroma::{chromagram, mean_chroma},
+        groove::GrooveTracker,
+        hpss::hpss,
+        key::estimate_key,
+        level::{is_silent, root_mean_square},
+        loudness::MIN_DB,
+        meter::{MeterTracker, Subdivision, subdivision},
+        segment::Segmenter,
+        stft::HOP_LENGTH,
+        tempo::{onset_strength, periodicity},
+        tempo_curve::TempoCurve,
+        tracker::{TempoObservation, TempoTracker},
+    },
+    applications::{
+        detector::{TempoDetector, TempoEstimate},
+        window::binary_transformer,
+    },
+    errors::handler::HandlerError,
+    models::packet::{
+        AnalysisJob, AnalysisResult, Beat, ChordResult, GrooveResult, HpssResult, MessagePack,
+        SectionResult, StereoResult, TempoCurvePoint, TrackSummary,
+    },
+};
+use common::protocol::ChannelMode;
+use std::ops::Range;
+use tokio::sync::mpsc::{Receiver, Sender, channel};
+
+pub type AnalysisJobSender = Sender<AnalysisJob>;
+pub type AnalysisResultReceiver = Receiver<Result<MessagePack, HandlerError>>;
+
+/// Start the analysis executor of a session.
+///
+/// The executor is a dedicated thread, so blocking analysis (librosa holds the GIL for
+/// hundreds of milliseconds) never stalls a tokio worker thread.
+/// Both queues hold at most `capacity` items. Once the job sender is dropped, the thread sends
+/// the summary of the track and exits.
+/// Every window is analyzed with `tempo_detector` on the signal of `channel_mode`, and on its
+/// percussive part if `hpss` is set.
+pub fn spawn_analysis_executor(
+    capacity: usize,
+    tempo_detector: Box<dyn TempoDetector>,
+    hpss: bool,
+    channel_mode: ChannelMode,
+) -> Result<(AnalysisJobSender, AnalysisResultReceiver), HandlerError> {
+    let (job_tx, mut job_rx) = channel::<AnalysisJob>(capacity);
+    let (result_tx, result_rx) = channel::<Result<MessagePack, HandlerError>>(capacity);
+
+    std::thread::Builder::new()
+        .name("analysis-executor".into())
+        .spawn(move || {
+            let mut session = SessionAnalysis::new(tempo_detector, hpss, channel_mode);
+            while let Some(job) = job_rx.blocking_recv() {
+                let results = match session.analyze(job) {
+                    Ok(message_packs) => message_packs.into_iter().map(Ok).collect(),
+                    Err(error) => vec![Err(error)],
+                };
+                for result in results {
+                    // the session is gone
+                    if result_tx.blocking_send(result).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = result_tx.blocking_send(Ok(MessagePack::Summary(session.summary())));
+        })?;
+
+    Ok((job_tx, result_rx))
+}
+
+/// The analysis state of a session, which lives on the executor thread.
+struct SessionAnalysis {
+    tempo_detector: Box<dyn TempoDetector>,
+    /// Whether the tempo comes from the percussive part of each window.
+    hpss: bool,
+    /// The signal of the stream that is analyzed.
+    channel_mode: ChannelMode,
+    tempo_tracker: TempoTracker,
+    /// The frame up to which the windows have been reported.
+    reported_frame: u64,
+    beat_grid: BeatGrid,
+    tempo_curve: TempoCurve,
+    /// Every point of the tempo curve so far, for the tempo curve of the track.
+    track_tempo_curve: Vec<TempoCurvePoint>,
+    /// The sum of the chroma of every window, for the key of the track.
+    track_chroma: [f32; 12],
+    chord_tracker: ChordTracker,
+    groove_tracker: GrooveTracker,
+    /// The section boundaries of the stream, created with the first window.
+    segmenter: Option<Segmenter>,
+}
+
+impl SessionAnalysis {
+    fn new(tempo_detector: Box<dyn TempoDetector>, hpss: bool, channel_mode: ChannelMode) -> Self {
+        SessionAnalysis {
+            tempo_detector,
+            hpss,
+            channel_mode,
+            tempo_tracker: TempoTracker::default(),
+            reported_frame: 0,
+            beat_grid: BeatGrid::default(),
+            tempo_curve: TempoCurve::default(),
+            track_tempo_curve: Vec::new(),
+            track_chroma: [0.0; 12],
+            chord_tracker: ChordTracker::default(),
+            groove_tracker: GrooveTracker::default(),
+            segmenter: None,
+        }
+    }
+
+    // blocking analysis of one window: the analysis, then the section changes it found
+    fn analyze(&mut self, job: AnalysisJob) -> Result<Vec<MessagePack>, HandlerError> {
+        // the windows overlap, so each one reports from where the previous one stopped to the
+        // middle of its overlap with the next one
+        let interior = job.start_frame.max(self.reported_frame)..job.interior_end_frame();
+        self.reported_frame = interior.end;
+        // Convert binary data to f32 samples based on audio info
+        let samples = binary_transformer(job.binary, &job.audio_info);
+        let sample_rate = job.audio_info.sample_rate;
+        let signals = deinterleave(&samples, job.audio_info.channels as usize);
+        let signal = select(&signals, self.channel_mode);
+        // pads and vocals pulse too, so the rhythm is taken from the drums if asked to
+        let separation = self.hpss.then(|| hpss(&signal, sample_rate));
+        let rhythm = separation.as_ref().map_or(signal.as_slice(), |separation| {
+            separation.percussive.as_slice()
+        });
+        let tempo = self.tempo_detector.detect(rhythm, sample_rate)?;
+        let envelope = onset_strength(rhythm, sample_rate);
+        let frame_rate = sample_rate as f32 / HOP_LENGTH as f32;
+        let tracked_tempo = self
+            .tempo_tracker
+            .update(tempo_observation(&signal, &envelope, frame_rate, &tempo));
+        // no beats without a steady tempo
+        let beats = match tracked_tempo.bpm {
+            Some(_) => {
+                // the low-frequency energy of each beat, relative to the window
+                let mut accents = low_frequency_energy(&signal, sample_rate, &tempo.beats);
+                let mean = accents.iter().sum::<f32>() / accents.len().max(1) as f32;
+                if mean > f32::EPSILON {
+                    accents.iter_mut().for_each(|accent| *accent /= mean);
+                }
+                self.beat_grid.merge(
+                    job.start_frame,
+                    interior.clone(),
+                    sample_rate,
+                    &tempo,
+                    &accents,
+                    subdivision(&envelope, frame_rate, &tempo.beats),
+                )
+            }
+            None => Vec::new(),
+        };
+        let tempo_curve: Vec<TempoCurvePoint> = beats
+            .iter()
+            .filter_map(|beat| {
+                let point = self.tempo_curve.push(beat.time)?;
+                Some(TempoCurvePoint {
+                    frame: beat.frame,
+                    time: point.time,
+                    bpm: point.bpm,
+                })
+            })
+            .collect();
+        self.track_tempo_curve.extend(tempo_curve.iter().cloned());
+
+        // the onsets of the window go in before its beats, which may end a bar
+        // (only the interior, so every onset is added once)
+        self.groove_tracker.push_onsets(
+            job.onset_frames
+                .iter()
+                .filter(|frame| interior.contains(frame))
+                .map(|frame| *frame as f64 / sample_rate as f64),
+        );
+        let grooves = beats
+            .iter()
+            .filter_map(|beat| {
+                self.groove_tracker
+                    .push_beat(beat.time, beat.bar, beat.beat_in_bar as usize)
+            })
+            .map(|groove| GrooveResult::new(groove, sample_rate))
+            .collect();
+
+        // the key and the chords come from the same chromagram
+        let window_chromagram = chromagram(&signal, sample_rate);
+        let chroma = mean_chroma(&window_chromagram);
+        for (sum, value) in self.track_chroma.iter_mut().zip(chroma) {
+            *sum += value;
+        }
+        let key = estimate_key(&chroma).map(Into::into);
+
+        let chords = self
+            .chord_tracker
+            .merge(
+                job.start_frame,
+                interior.clone(),
+                &recognize_chords(&window_chromagram, &signal),
+            )
+            .into_iter()
+            .map(|segment| ChordResult::new(segment, sample_rate))
+            .collect();
+
+        // the windows overlap, so the segmenter only takes the frames it has not seen yet
+        let section_changes = self
+            .segmenter
+            .get_or_insert_with(|| Segmenter::new(sample_rate))
+            .process(&signal, job.start_frame);
+
+        let stereo = (!signals.iter().all(|signal| is_silent(signal))).then(|| {
+            let image = stereo_image(&signals);
+            StereoResult {
+                levels: signals.iter().map(|signal| level(signal)).collect(),
+                correlation: image.as_ref().map(|image| image.correlation),
+                width: image.map(|image| image.width),
+            }
+        });
+
+        let mut message_packs = vec![MessagePack::Analysis(AnalysisResult {
+            start_frame: job.start_frame,
+            end_frame: job.end_frame,
+            bpm: tracked_tempo.bpm,
+            confidence: tracked_tempo.confidence,
+            beats,
+            tempo_curve,
+            meter: self.beat_grid.meter_tracker.meter(),
+            key,
+            chords,
+            hpss: separation.map(|separation| HpssResult {
+                harmonic_level: level(&separation.harmonic),
+                percussive_level: level(&separation.percussive),
+                percussive_ratio: separation.percussive_energy
+                    / (separation.harmonic_energy + separation.percussive_energy)
+                        .max(f32::MIN_POSITIVE),
+            }),
+            stereo,
+            grooves,
+        })];
+        message_packs.extend(section_changes.into_iter().map(|change| {
+            MessagePack::SectionChange(SectionResult::from_change(change, sample_rate))
+        }));
+        Ok(message_packs)
+    }
+
+    // the analysis of every window so far
+    fn summary(&self) -> TrackSummary {
+        TrackSummary {
+            key: estimate_key(&self.track_chroma).map(Into::into),
+            meter: self.beat_grid.meter_tracker.meter(),
+            sections: self.segmenter.as_ref().map_or(Vec::new(), |segmenter| {
+                segmenter
+                    .sections()
+                    .into_iter()
+                    .map(|section| SectionResult::from_section(section, segmenter.sample_rate()))
+                    .collect()
+            }),
+            tempo_curve: self.track_tempo_curve.clone(),
+            groove: self.groove_tracker.summary(),
+        }
+    }
+}
+
+// the RMS level of `samples` in dBFS (at least `MIN_DB`)
+fn level(samples: &[f32]) -> f32 {
+    (20.0 * root_mean_square(samples).max(1e-6).log10()).max(MIN_DB)
+}
+
+// the window estimate weighted by how periodic the window is (`None` if it is silent)
+fn tempo_observation(
+    samples: &[f32],
+    envelope: &[f32],
+    frame_rate: f32,
+    tempo: &TempoEstimate,
+) -> Option<TempoObservation> {
+    if tempo.bpm <= 0.0 || is_silent(samples) {
+        return None;
+    }
+    Some(TempoObservation {
+        bpm: tempo.bpm,
+        strength: periodicity(envelope, frame_rate, tempo.bpm as f32) as f64,
+    })
+}
+
+/// The beats reported so far, so the overlap of two windows is not reported twice.
+#[derive(Default)]
+struct BeatGrid {
+    /// The time of the last reported beat in seconds from the start of the stream.
+    last_beat: Option<f64>,
+    /// The meter and the bars of the reported beats.
+    meter_tracker: MeterTracker,
+}
+
+impl BeatGrid {
+    /// Map the beats of a window onto the stream timeline, keep the ones in `frames` (and not
+    /// too close to the last reported beat) and place them in the bars with their `accents`.
+    fn merge(
+        &mut self,
+        start_frame: u64,
+        frames: Range<u64>,
+        sample_rate: u32,
+        tempo: &TempoEstimate,
+        accents: &[f32],
+        subdivision: Subdivision,
+    ) -> Vec<Beat> {
+        self.meter_tracker.observe(subdivision);
+        let start_time = start_frame as f64 / sample_rate as f64;
+        // a beat closer than half a period to the last one is the same beat
+        let min_gap = if tempo.bpm > 0.0 {
+            30.0 / tempo.bpm
+        } else {
+            0.1
+        };
+
+        let mut beats = Vec::new();
+        for (beat, accent) in tempo.beats.iter().zip(accents) {
+            let time = start_time + beat;
+            let frame = (time * sample_rate as f64).round() as u64;
+            if !frames.contains(&frame)
+                || self
+                    .last_beat
+                    .is_some_and(|last_beat| time < last_beat + min_gap)
+            {
+                continue;
+            }
+            let position = self.meter_tracker.push(*accent);
+            beats.push(Beat {
+                frame,
+                time,
+                downbeat: position.beat_in_bar == 0,
+                bar: position.bar,
+                beat_in_bar: position.beat_in_bar as u32,
+            });
+            self.last_beat = Some(time);
+        }
+        beats
+    }
+}
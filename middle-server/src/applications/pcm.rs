@@ -1,44 +1,243 @@
-use crate::{errors::handler::HandlerError, models::packet::WindowPacket};
+use crate::{
+    analysis::spectrum::{DEFAULT_BANDS, DEFAULT_RATE, MAX_BANDS, MAX_RATE, MIN_RATE},
+    applications::stream_detectors::{
+        StreamDetectorSettings, StreamPacketSender, StreamResultReceiver, spawn_stream_detectors,
+    },
+    errors::handler::HandlerError,
+    models::{
+        audio::RwLockAudioInfo,
+        config::Config,
+        options::RwLockAnalysisOptions,
+        packet::{MessagePack, PcmPacket, WindowPacket},
+        window::{PcmSettings, StreamLength},
+        ws::MutexWebSocketClientWriter,
+    },
+};
+use common::protocol::AnalysisOptions;
+use futures_util::SinkExt;
 use std::collections::VecDeque;
+use tokio::sync::mpsc::error::TrySendError;
 
 // [task3] pcm data processing
+/*
+    Overlapping sliding window over the PCM stream:
+    every window covers exactly `window_length` and the next one starts `hop_length` later.
+
+    e.g. window 4s, hop 2s
+    |--- window 0 ---|
+            |--- window 1 ---|
+                    |--- window 2 ---|
+
+    The levels, the spectrum, the onsets and the pitch are measured on every packet on the way,
+    so they reach the client right away. The stream detectors run on a dedicated thread:
+    packets go to it through a bounded queue, and their results come back through a bounded
+    queue and are sent to the client as they arrive.
+    Each window carries the onsets found in it, for the groove of the analysis.
+*/
 pub async fn pcm_data_processing(
-    window_size: u64,
-    slide_size: u64,
-    mut pcm_rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    settings: PcmSettings,
+    config: Config,
+    mut pcm_rx: tokio::sync::mpsc::Receiver<PcmPacket>,
     window_tx: tokio::sync::mpsc::Sender<WindowPacket>,
+    shared_client_writer: MutexWebSocketClientWriter,
+    shared_audio_info: RwLockAudioInfo,
+    shared_analysis_options: RwLockAnalysisOptions,
 ) -> Result<(), HandlerError> {
-    let mut counter: u64 = 0;
-    let mut stock_buffer: VecDeque<Vec<u8>> = VecDeque::new();
-    let mut window_packet: Vec<u8> = Vec::new();
-
-    //* step6: receive binary from sender (producer) *//
-    //* step7: do sliding window (while loop) *//
-    //? Receiver (Consumer) //
-    while let Some(bin) = pcm_rx.recv().await {
-        //* collect buffer *//
-        stock_buffer.push_back(bin);
-        counter += 1;
-
-        tracing::info!("counter: {}", counter);
-
-        if counter >= window_size {
-            // create window packet
-            for _ in 0..slide_size {
-                if let Some(buf) = stock_buffer.pop_front() {
-                    window_packet.extend(buf);
+    // (bytes per frame, window size in frames, hop size in frames), known once PCM data arrives
+    let mut layout: Option<(usize, u64, u64)> = None;
+    // ring buffer of the PCM data that has not been slid past yet
+    let mut ring_buffer: VecDeque<u8> = VecDeque::new();
+    // the index of the first frame in the ring buffer
+    let mut ring_start_frame: u64 = 0;
+    // the frames of the onsets that have not been slid past yet
+    let mut onset_frames: VecDeque<u64> = VecDeque::new();
+    // the stream detectors, started with the layout
+    // (the packet sender is dropped at the end of the stream, which lets the thread finish)
+    let mut packet_tx: Option<StreamPacketSender> = None;
+    let mut result_rx: Option<StreamResultReceiver> = None;
+    let mut pcm_open = true;
+
+    loop {
+        tokio::select! {
+            //* step8: receive binary from sender (producer) *//
+            //? Receiver (Consumer) //
+            packet = pcm_rx.recv(), if pcm_open => {
+                let Some(packet) = packet else {
+                    // no more PCM data: close the packet queue and drain the results
+                    pcm_open = false;
+                    packet_tx = None;
+                    if result_rx.is_none() {
+                        break;
+                    }
+                    continue;
+                };
+
+                let (bytes_per_frame, window_frames, hop_frames) = match layout {
+                    Some(layout) => layout,
+                    None => {
+                        let rwlock_audio_info = shared_audio_info.read().await;
+                        let audio_info = rwlock_audio_info
+                            .as_ref()
+                            .ok_or(HandlerError::AudioInfoUndefinedError)?;
+                        let window_frames =
+                            settings.window_length.to_frames(audio_info.sample_rate);
+                        // a hop longer than the window would skip audio,
+                        // so it is clamped to the window
+                        let hop_frames = settings
+                            .hop_length
+                            .to_frames(audio_info.sample_rate)
+                            .min(window_frames);
+                        let new_layout = (audio_info.bytes_per_frame(), window_frames, hop_frames);
+                        let audio_info = audio_info.clone();
+                        drop(rwlock_audio_info); // release the lock
+                        let options = shared_analysis_options.read().await.clone();
+                        let detector_settings = stream_detector_settings(
+                            &settings,
+                            &config,
+                            &options,
+                            audio_info.sample_rate,
+                        );
+                        let (tx, rx) = spawn_stream_detectors(
+                            settings.detector_queue_capacity,
+                            audio_info,
+                            detector_settings,
+                            packet.start_frame,
+                        )?;
+                        packet_tx = Some(tx);
+                        result_rx = Some(rx);
+                        tracing::info!(
+                            "Sliding window: {} frames, hop {} frames",
+                            window_frames,
+                            hop_frames
+                        );
+                        *layout.insert(new_layout)
+                    }
+                };
+                let window_bytes = window_frames as usize * bytes_per_frame;
+                let hop_bytes = hop_frames as usize * bytes_per_frame;
+
+                //* hand the packet to the stream detectors without waiting for them *//
+                if let Some(packet_tx) = &packet_tx {
+                    match packet_tx.try_send(PcmPacket {
+                        start_frame: packet.start_frame,
+                        end_frame: packet.end_frame,
+                        binary: packet.binary.clone(),
+                    }) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(packet)) => {
+                            tracing::warn!(
+                                "Stream detectors are lagging behind, skipping frames {}..{}",
+                                packet.start_frame,
+                                packet.end_frame
+                            );
+                        }
+                        Err(error) => return Err(error.into()),
+                    }
+                }
+
+                //* collect buffer *//
+                // a chunk skipped while the analysis was lagging breaks the window,
+                // so start over after the gap
+                let ring_end_frame =
+                    ring_start_frame + (ring_buffer.len() / bytes_per_frame) as u64;
+                if packet.start_frame != ring_end_frame {
+                    tracing::warn!(
+                        "PCM data jumped from frame {} to {}, restarting the window",
+                        ring_end_frame,
+                        packet.start_frame
+                    );
+                    ring_buffer.clear();
+                    ring_start_frame = packet.start_frame;
+                }
+                ring_buffer.extend(packet.binary);
+
+                //* step9: do sliding window (while loop) *//
+                while ring_buffer.len() >= window_bytes {
+                    // create window packet
+                    let binary: Vec<u8> = ring_buffer.range(..window_bytes).copied().collect();
+
+                    //* step10: send window packet to window_data_processing with window size *//
+                    //? Sender (Producer) //
+                    window_tx
+                        .send(WindowPacket {
+                            start_frame: ring_start_frame,
+                            end_frame: ring_start_frame + window_frames,
+                            hop_frames,
+                            binary,
+                            onset_frames: onset_frames
+                                .iter()
+                                .copied()
+                                .filter(|frame| {
+                                    (ring_start_frame..ring_start_frame + window_frames)
+                                        .contains(frame)
+                                })
+                                .collect(),
+                        })
+                        .await?;
+
+                    // slide the window by the hop size
+                    ring_buffer.drain(..hop_bytes);
+                    ring_start_frame += hop_frames;
+                    while onset_frames
+                        .front()
+                        .is_some_and(|frame| *frame < ring_start_frame)
+                    {
+                        onset_frames.pop_front();
+                    }
                 }
             }
+            message_pack = recv_result(&mut result_rx) => {
+                // the stream detectors have finished every packet
+                let Some(message_pack) = message_pack else {
+                    break;
+                };
 
-            //* step8: send window packet to window_data_processing with window size *//
-            //? Sender (Producer) //
-            window_tx.send(WindowPacket(window_packet.clone())).await?;
+                // the detectors get every packet before it is windowed, and the analysis only
+                // reports a window up to the middle of its overlap with the next one, so the
+                // onsets of that part are in by the time the window is cut
+                if let MessagePack::Onset(onset) = &message_pack {
+                    onset_frames.push_back(onset.frame);
+                }
 
-            // reset counter
-            counter -= slide_size;
-            // reset send buffer
-            window_packet.clear();
+                //* send the levels, the spectrum, the onsets and the pitch to the client *//
+                let mut writer = shared_client_writer.lock().await;
+                writer
+                    .send(message_pack.to_message()?)
+                    .await
+                    .map_err(HandlerError::AxumError)?;
+            }
         }
     }
     Ok(())
 }
+
+// wait for the next result, or forever if the stream detectors are not started
+async fn recv_result(result_rx: &mut Option<StreamResultReceiver>) -> Option<MessagePack> {
+    match result_rx {
+        Some(result_rx) => result_rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+// the stream detector settings chosen by the session
+fn stream_detector_settings(
+    settings: &PcmSettings,
+    config: &Config,
+    options: &AnalysisOptions,
+    sample_rate: u32,
+) -> StreamDetectorSettings {
+    let rate = options
+        .spectrum_rate
+        .filter(|rate| rate.is_finite())
+        .map_or(DEFAULT_RATE, |rate| rate.clamp(MIN_RATE, MAX_RATE));
+    StreamDetectorSettings {
+        loudness_frames: settings.loudness_interval.to_frames(sample_rate),
+        spectrum_bands: options
+            .spectrum_bands
+            .map_or(DEFAULT_BANDS, |bands| (bands as usize).min(MAX_BANDS)),
+        spectrum_frames: StreamLength::Seconds(1.0 / rate).to_frames(sample_rate),
+        channel_mode: options.channel_mode.unwrap_or_default(),
+        // the onsets follow the drums too if the rhythm is taken from them
+        hpss: options.hpss.unwrap_or(config.hpss),
+    }
+}
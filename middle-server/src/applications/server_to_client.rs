@@ -1,64 +1,123 @@
 use crate::{
     errors::handler::HandlerError,
     models::{
-        audio::{AudioInfo, RwLockAudioInfo},
+        audio::RwLockAudioInfo,
+        packet::{MessagePack, PcmPacket},
         ws::{MutexWebSocketClientWriter, WebSocketServerReader},
     },
 };
-use axum::extract::ws::Message;
+use common::{
+    audio::AudioInfo,
+    errors::protocol::ProtocolError,
+    protocol::{ControlFrame, ControlMessage},
+};
 use futures_util::{SinkExt, StreamExt};
+use tokio::sync::mpsc::error::TrySendError;
 use tokio_tungstenite::tungstenite;
 
 // [task2] server -> client
 pub async fn handle_server_to_client(
     mut server_reader: WebSocketServerReader,
-    pcm_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    pcm_tx: tokio::sync::mpsc::Sender<PcmPacket>,
     shared_client_writer: MutexWebSocketClientWriter,
     shared_audio_info: RwLockAudioInfo,
 ) -> Result<(), HandlerError> {
+    // local copy of the audio info, so forwarding a chunk does not wait for the lock
+    let mut audio_info: Option<AudioInfo> = None;
+    // the index of the next frame in the stream
+    let mut next_frame: u64 = 0;
+
     while let Some(Ok(message)) = server_reader.next().await {
         match message {
             tungstenite::Message::Text(text) => {
-                //* step2: receive audio info from server *//
-                tracing::info!("Received text from client: {:?}", text);
-                // set audio info
-                let mut shared_audio_info = shared_audio_info.write().await;
-                *shared_audio_info = AudioInfo::try_from(text.clone()).map_err(|e| {
-                    tracing::error!("Failed to parse audio info: {:?}", e);
-                    HandlerError::ParseAudioInfoError(e.to_string())
-                })?;
-                drop(shared_audio_info); // release the lock
-                //* step3: send audio info to client *//
+                tracing::info!("Received text from server: {:?}", text);
+                // reject unknown messages and unsupported protocol versions
+                let frame = ControlFrame::from_json(text.as_str())?;
+                let message_pack = match frame.message {
+                    //* step2: receive audio info from server *//
+                    ControlMessage::StreamHeader(header) => {
+                        header.validate().inspect_err(|e| {
+                            tracing::error!("Failed to parse audio info: {:?}", e);
+                        })?;
+                        // set audio info
+                        let mut shared_audio_info = shared_audio_info.write().await;
+                        *shared_audio_info = Some(header.clone());
+                        drop(shared_audio_info); // release the lock
+                        audio_info = Some(header.clone());
+                        MessagePack::StreamHeader(header)
+                    }
+                    ControlMessage::Hello => MessagePack::Hello {
+                        version: frame.version,
+                    },
+                    ControlMessage::Error { reason } => MessagePack::Error { reason },
+                    //* the server has sent the whole stream *//
+                    // the end of stream is sent to the client once the analysis is drained
+                    ControlMessage::Bye { reason } => {
+                        tracing::info!("Server said bye: {:?}", reason);
+                        break;
+                    }
+                    ControlMessage::Open { .. } | ControlMessage::Accept => {
+                        return Err(ProtocolError::UnexpectedMessageError(
+                            "open and accept are only sent by the client".into(),
+                        )
+                        .into());
+                    }
+                };
+                //* step3: send audio info (or hello, error) to client *//
                 let mut writer = shared_client_writer.lock().await;
                 writer
-                    .send(Message::Text(text.to_string().into()))
+                    .send(message_pack.to_message()?)
                     .await
                     .map_err(HandlerError::AxumError)?;
             }
             tungstenite::Message::Binary(binary) => {
                 //* step5: receive PCM data from server *//
-                tracing::info!("Received binary from client: {:?}", binary);
-                //? Sender (Producer) //
-                pcm_tx.send(binary.to_vec()).await?;
-            }
-            tungstenite::Message::Close(close) => {
-                tracing::info!("Server disconnected: {:?}", close);
+                tracing::debug!("Received {} bytes of PCM data from server", binary.len());
+                let audio_info = audio_info
+                    .as_ref()
+                    .ok_or(HandlerError::AudioInfoUndefinedError)?;
+                let start_frame = next_frame;
+                next_frame += (binary.len() / audio_info.bytes_per_frame()) as u64;
+
+                //* step6: forward PCM data to client right away *//
+                let message_pack = MessagePack::AudioChunk {
+                    start_frame,
+                    pcm: binary.to_vec(),
+                };
                 let mut writer = shared_client_writer.lock().await;
-                //? send close frame to client //
-                // convert tungstenite CloseFrame to axum CloseFrame
-                let axum_close = close.map(|close_frame| axum::extract::ws::CloseFrame {
-                    code: axum::extract::ws::CloseCode::from(u16::from(close_frame.code)),
-                    reason: close_frame.reason.to_string().into(),
-                });
-                // send close frame to client
                 writer
-                    .send(axum::extract::ws::Message::Close(axum_close))
+                    .send(message_pack.to_message()?)
                     .await
                     .map_err(HandlerError::AxumError)?;
+                drop(writer); // release the lock
+
+                //* step7: hand PCM data to the analysis without waiting for it *//
+                //? Sender (Producer) //
+                match pcm_tx.try_send(PcmPacket {
+                    start_frame,
+                    end_frame: next_frame,
+                    binary: binary.to_vec(),
+                }) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(packet)) => {
+                        tracing::warn!(
+                            "Analysis is lagging behind, skipping frames {}..{}",
+                            packet.start_frame,
+                            packet.end_frame
+                        );
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            tungstenite::Message::Close(close) => {
+                //* the server closed the connection *//
+                // the client is closed once the analysis is drained
+                tracing::info!("Server disconnected: {:?}", close);
+                break;
             }
             _ => {
                 tracing::error!("Received unsupported message type from server");
-                return Err(HandlerError::UnexpectedMessageTypeError);
+                return Err(ProtocolError::UnexpectedMessageTypeError.into());
             }
         }
     }
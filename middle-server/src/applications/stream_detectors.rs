@@ -0,0 +1,207 @@
+use crate::{
+    analysis::{
+        channel::{deinterleave, select},
+        hpss::PercussiveFilter,
+        loudness::LoudnessMeter,
+        onset::OnsetDetector,
+        pitch::PitchTracker,
+        spectrum::{SpectrumAnalyzer, quantize},
+    },
+    applications::window::binary_transformer,
+    errors::handler::HandlerError,
+    models::packet::{LoudnessResult, MessagePack, OnsetResult, PcmPacket, PitchResult},
+};
+use common::{audio::AudioInfo, protocol::ChannelMode};
+use tokio::sync::mpsc::{Receiver, Sender, channel};
+
+pub type StreamPacketSender = Sender<PcmPacket>;
+pub type StreamResultReceiver = Receiver<MessagePack>;
+
+/// What the stream detectors of a session measure.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamDetectorSettings {
+    /// The frames of one loudness reading.
+    pub loudness_frames: u64,
+    /// The number of spectrum bands (no spectrum if 0).
+    pub spectrum_bands: usize,
+    /// The frames from one spectrum to the next.
+    pub spectrum_frames: u64,
+    /// The signal of the stream that the onsets and the pitch follow.
+    pub channel_mode: ChannelMode,
+    /// Whether the onsets are detected in the percussive part only, like the rhythm of the
+    /// analysis (they arrive about 0.2 s later).
+    pub hpss: bool,
+}
+
+/// Start the stream detectors of a session (the loudness meter, the spectrum analyzer, the
+/// onset detector and the pitch tracker) for a stream that starts at `start_frame`.
+///
+/// They process every sample of the stream (the spectrum, the onsets and the pitch run FFTs
+/// every few milliseconds of audio), so they live on a dedicated thread and never stall a tokio
+/// worker thread.
+/// Both queues hold at most `capacity` items. A packet that does not follow the previous one
+/// (e.g. after a packet was skipped) restarts the detectors. Once the packet sender is dropped,
+/// the thread exits.
+pub fn spawn_stream_detectors(
+    capacity: usize,
+    audio_info: AudioInfo,
+    settings: StreamDetectorSettings,
+    start_frame: u64,
+) -> Result<(StreamPacketSender, StreamResultReceiver), HandlerError> {
+    let (packet_tx, mut packet_rx) = channel::<PcmPacket>(capacity);
+    let (result_tx, result_rx) = channel::<MessagePack>(capacity);
+
+    std::thread::Builder::new()
+        .name("stream-detectors".into())
+        .spawn(move || {
+            let mut detectors = StreamDetectors::new(audio_info, settings, start_frame);
+            let mut next_frame = start_frame;
+            while let Some(packet) = packet_rx.blocking_recv() {
+                if packet.start_frame != next_frame {
+                    detectors.reset(packet.start_frame);
+                }
+                next_frame = packet.end_frame;
+                for message_pack in detectors.process(packet.binary) {
+                    // the session is gone
+                    if result_tx.blocking_send(message_pack).is_err() {
+                        return;
+                    }
+                }
+            }
+        })?;
+
+    Ok((packet_tx, result_rx))
+}
+
+/// The detectors that follow every packet of the stream.
+struct StreamDetectors {
+    audio_info: AudioInfo,
+    settings: StreamDetectorSettings,
+    loudness_meter: LoudnessMeter,
+    /// The first frame of the next loudness reading.
+    loudness_frame: u64,
+    spectrum_analyzer: Option<SpectrumAnalyzer>,
+    /// The frame after the last analyzed frame.
+    spectrum_frame: u64,
+    /// The percussive part of the signal that the onset detector follows, if asked to.
+    percussive_filter: Option<PercussiveFilter>,
+    onset_detector: OnsetDetector,
+    pitch_tracker: PitchTracker,
+}
+
+impl StreamDetectors {
+    fn new(audio_info: AudioInfo, settings: StreamDetectorSettings, start_frame: u64) -> Self {
+        // the levels and the spectrum are measured on every channel,
+        // the onsets and the pitch on the selected signal only
+        StreamDetectors {
+            loudness_meter: new_loudness_meter(&audio_info, &settings),
+            loudness_frame: start_frame,
+            spectrum_analyzer: new_spectrum_analyzer(&audio_info, &settings),
+            spectrum_frame: start_frame,
+            percussive_filter: settings.hpss.then(PercussiveFilter::default),
+            onset_detector: OnsetDetector::new(1, audio_info.sample_rate, start_frame),
+            pitch_tracker: PitchTracker::new(1, audio_info.sample_rate, start_frame),
+            audio_info,
+            settings,
+        }
+    }
+
+    // restart after a gap in the stream
+    fn reset(&mut self, start_frame: u64) {
+        self.loudness_meter = new_loudness_meter(&self.audio_info, &self.settings);
+        self.loudness_frame = start_frame;
+        self.spectrum_analyzer = new_spectrum_analyzer(&self.audio_info, &self.settings);
+        self.spectrum_frame = start_frame;
+        if let Some(percussive_filter) = self.percussive_filter.as_mut() {
+            *percussive_filter = PercussiveFilter::default();
+        }
+        self.onset_detector.reset(start_frame);
+        self.pitch_tracker.reset(start_frame);
+    }
+
+    // the levels of every completed loudness block, the spectrum of every hop, the onsets of a
+    // packet, then its pitch frames as one message
+    fn process(&mut self, binary: Vec<u8>) -> Vec<MessagePack> {
+        let sample_rate = self.audio_info.sample_rate as f64;
+        let samples = binary_transformer(binary, &self.audio_info);
+        let loudness_frames = self.settings.loudness_frames;
+        let mut message_packs: Vec<MessagePack> = Vec::new();
+        for reading in self.loudness_meter.process(&samples) {
+            message_packs.push(MessagePack::Loudness(LoudnessResult {
+                start_frame: self.loudness_frame,
+                end_frame: self.loudness_frame + loudness_frames,
+                rms: reading.rms,
+                sample_peak: reading.sample_peak,
+                true_peak: reading.true_peak,
+                momentary: reading.momentary,
+                short_term: reading.short_term,
+                clipping: reading.clipping,
+            }));
+            self.loudness_frame += loudness_frames;
+        }
+        if let Some(spectrum_analyzer) = self.spectrum_analyzer.as_mut() {
+            for spectrum in spectrum_analyzer.process(&samples) {
+                self.spectrum_frame += self.settings.spectrum_frames;
+                message_packs.push(MessagePack::Spectrum {
+                    frame: self.spectrum_frame,
+                    bands: spectrum.into_iter().map(quantize).collect(),
+                });
+            }
+        }
+
+        let samples = select(
+            &deinterleave(&samples, self.audio_info.channels as usize),
+            self.settings.channel_mode,
+        );
+        // the percussive part starts with the stream, so the onsets keep their frames
+        let percussive = self
+            .percussive_filter
+            .as_mut()
+            .map(|percussive_filter| percussive_filter.process(&samples));
+        let rhythm = percussive.as_deref().unwrap_or(&samples);
+        for onset in self.onset_detector.process(rhythm) {
+            message_packs.push(MessagePack::Onset(OnsetResult {
+                frame: onset.frame,
+                time: onset.frame as f64 / sample_rate,
+                strength: onset.strength,
+                instrument: onset.instrument,
+            }));
+        }
+        let pitch_frames = self.pitch_tracker.process(&samples);
+        if let Some(first) = pitch_frames.first() {
+            message_packs.push(MessagePack::Pitch(PitchResult {
+                start_frame: first.frame,
+                start_time: first.frame as f64 / sample_rate,
+                hop_frames: self.pitch_tracker.hop_frames() as u32,
+                f0: pitch_frames
+                    .iter()
+                    .map(|frame| frame.f0.unwrap_or(0.0))
+                    .collect(),
+                voicing: pitch_frames.iter().map(|frame| frame.voicing).collect(),
+            }));
+        }
+        message_packs
+    }
+}
+
+fn new_loudness_meter(audio_info: &AudioInfo, settings: &StreamDetectorSettings) -> LoudnessMeter {
+    LoudnessMeter::new(
+        audio_info.channels as usize,
+        audio_info.sample_rate,
+        settings.loudness_frames as usize,
+    )
+}
+
+fn new_spectrum_analyzer(
+    audio_info: &AudioInfo,
+    settings: &StreamDetectorSettings,
+) -> Option<SpectrumAnalyzer> {
+    (settings.spectrum_bands > 0).then(|| {
+        SpectrumAnalyzer::new(
+            audio_info.channels as usize,
+            audio_info.sample_rate,
+            settings.spectrum_bands,
+            settings.spectrum_frames as usize,
+        )
+    })
+}
@@ -1,87 +1,170 @@
 use crate::{
+    applications::{
+        detector::create_tempo_detector,
+        executor::{AnalysisJobSender, AnalysisResultReceiver, spawn_analysis_executor},
+    },
     errors::handler::HandlerError,
     models::{
-        audio::{RwLockAudioInfo, UnwrappedAudioInfo},
-        packet::{MessagePack, WindowPacket},
+        audio::RwLockAudioInfo,
+        config::Config,
+        options::RwLockAnalysisOptions,
+        packet::{AnalysisJob, MessagePack, WindowPacket},
         ws::MutexWebSocketClientWriter,
     },
 };
-use axum::extract::ws::Message;
+use common::audio::{AudioInfo, SampleFormat};
 use futures_util::SinkExt;
-use numpy::IntoPyArray;
-use pyo3::{
-    PyResult, Python,
-    types::{PyAnyMethods, PyDict},
-};
+use tokio::sync::mpsc::error::TrySendError;
 
 // [task4] window data processing
-// TODO: ここで時間のかかる解析処理を実行する
+/*
+    The analysis itself runs on the analysis executor (a dedicated thread):
+    windows go to the executor through a bounded job queue,
+    results (and the summary of the track) come back through a bounded result queue
+    and are sent to the client as they arrive.
+    The executor is started with the first window, once the client has chosen its analysis options.
+*/
 pub async fn window_data_processing(
+    analysis_queue_capacity: usize,
+    config: Config,
     mut window_rx: tokio::sync::mpsc::Receiver<WindowPacket>,
     shared_client_writer: MutexWebSocketClientWriter,
     shared_audio_info: RwLockAudioInfo,
+    shared_analysis_options: RwLockAnalysisOptions,
 ) -> Result<(), HandlerError> {
-    //? Receiver (Consumer) //
-    while let Some(window_packet) = window_rx.recv().await {
-        let binary = window_packet.0;
+    // dropped at the end of the stream, which lets the executor finish
+    let mut job_tx: Option<AnalysisJobSender> = None;
+    let mut result_rx: Option<AnalysisResultReceiver> = None;
+    let mut window_open = true;
 
-        //* step9: analyze pcm data *//
-        let rwlock_audio_info = shared_audio_info.read().await;
-        let audio_info = rwlock_audio_info.get_audio_info().map_err(|e| {
-            tracing::error!("Failed to get audio info: {:?}", e);
-            HandlerError::AudioInfoUndefinedError
-        })?;
-        drop(rwlock_audio_info); // release the lock
+    loop {
+        tokio::select! {
+            //? Receiver (Consumer) //
+            window_packet = window_rx.recv(), if window_open => {
+                let Some(window_packet) = window_packet else {
+                    // no more windows: close the job queue and drain the results
+                    window_open = false;
+                    job_tx = None;
+                    if result_rx.is_none() {
+                        break;
+                    }
+                    continue;
+                };
 
-        // Convert binary data to f32 samples based on audio info
-        let samples = binary_transformer(binary.clone(), &audio_info);
-        let bpm = Python::with_gil(|py| pcm_detector(py, samples, audio_info.sample_rate as f64))?;
+                let rwlock_audio_info = shared_audio_info.read().await;
+                let audio_info = rwlock_audio_info.clone().ok_or_else(|| {
+                    tracing::error!("Failed to get audio info: not set");
+                    HandlerError::AudioInfoUndefinedError
+                })?;
+                drop(rwlock_audio_info); // release the lock
 
-        //* step10: create message pack *//
-        let message_pack = rmp_serde::to_vec_named(&MessagePack {
-            pcm: binary.clone(),
-            bpm,
-        })?;
+                //* step11: hand the window to the analysis executor *//
+                if result_rx.is_none() {
+                    let (tx, rx) = start_executor(
+                        analysis_queue_capacity,
+                        &config,
+                        &shared_analysis_options,
+                    )
+                    .await?;
+                    job_tx = Some(tx);
+                    result_rx = Some(rx);
+                }
+                let job = AnalysisJob {
+                    start_frame: window_packet.start_frame,
+                    end_frame: window_packet.end_frame,
+                    hop_frames: window_packet.hop_frames,
+                    binary: window_packet.binary,
+                    onset_frames: window_packet.onset_frames,
+                    audio_info,
+                };
+                if let Some(job_tx) = &job_tx {
+                    match job_tx.try_send(job) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(job)) => {
+                            tracing::warn!(
+                                "Analysis executor is busy, skipping window {}..{}",
+                                job.start_frame,
+                                job.end_frame
+                            );
+                        }
+                        Err(error) => return Err(error.into()),
+                    }
+                }
+            }
+            result = recv_result(&mut result_rx) => {
+                // the executor has finished every job
+                let Some(result) = result else {
+                    break;
+                };
 
-        //* step11: send messagepack to client *//
-        let mut writer = shared_client_writer.lock().await;
-        writer.send(Message::Binary(message_pack.into())).await?;
+                //* step12: receive the analysis of a window (or the summary of the track) *//
+                let message_pack = result?;
+
+                //* step13: send messagepack to client *//
+                let mut writer = shared_client_writer.lock().await;
+                writer.send(message_pack.to_message()?).await?;
+            }
+        }
     }
-    Ok(())
-}
 
-//TODO: 正しさの検証をする (正しくはない)
-fn binary_transformer(binary: Vec<u8>, _audio_info: &UnwrappedAudioInfo) -> Vec<f32> {
-    binary
-        .chunks_exact(2) // 1. バイト列を2バイトずつのチャンクに分割
-        .map(|chunk| {
-            // 2. 2バイトのスライスをi16に変換 (リトルエンディアン)
-            // try_into()で&[u8]を[u8; 2]に変換
-            let val_i16 = i16::from_le_bytes(chunk.try_into().unwrap());
-            // 3. i16の値をf32にキャストし、正規化
-            val_i16 as f32 / 32768.0
-        })
-        .collect() // 4. 結果をVec<f32>に集める
+    //* step14: the stream is complete once every window has been analyzed *//
+    let mut writer = shared_client_writer.lock().await;
+    writer.send(MessagePack::EndOfStream.to_message()?).await?;
+    Ok(())
 }
 
-fn pcm_detector<'py>(py: Python<'py>, samples: Vec<f32>, sample_rate: f64) -> PyResult<f64> {
-    // [python code]
-    // import librosa
-    let librosa = py.import("librosa")?;
+// start the analysis executor with the tempo detector, the separation and the channel mode
+// chosen by the session
+async fn start_executor(
+    capacity: usize,
+    config: &Config,
+    shared_analysis_options: &RwLockAnalysisOptions,
+) -> Result<(AnalysisJobSender, AnalysisResultReceiver), HandlerError> {
+    let options = shared_analysis_options.read().await.clone();
+    let tempo_detector_kind = options.tempo_detector.unwrap_or(config.tempo_detector);
+    let hpss = options.hpss.unwrap_or(config.hpss);
+    let channel_mode = options.channel_mode.unwrap_or_default();
+    tracing::info!(
+        "Tempo detector: {}, HPSS: {}, channel mode: {}",
+        tempo_detector_kind,
+        hpss,
+        channel_mode
+    );
 
-    // [python code]
-    // kwargs = {"y": samples, "sr": sample_rate}
-    let kwargs = PyDict::new(py);
-    kwargs.set_item("y", samples.into_pyarray(py))?;
-    kwargs.set_item("sr", sample_rate)?;
+    // creating a detector may import Python modules, which blocks
+    let config = config.clone();
+    let tempo_detector =
+        tokio::task::spawn_blocking(move || create_tempo_detector(tempo_detector_kind, &config))
+            .await??;
+    spawn_analysis_executor(capacity, tempo_detector, hpss, channel_mode)
+}
 
-    // [python code]
-    // tempo, _beats = librosa.beat.beat_track(kwargs)
-    let (tempo, _beats) = librosa
-        .getattr("beat")?
-        .getattr("beat_track")?
-        .call((), Some(&kwargs))?
-        .extract::<(f64, pyo3::PyObject)>()?;
+// wait for the next result, or forever if the executor is not started
+async fn recv_result(
+    result_rx: &mut Option<AnalysisResultReceiver>,
+) -> Option<Result<MessagePack, HandlerError>> {
+    match result_rx {
+        Some(result_rx) => result_rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
-    Ok(tempo)
+// Convert little-endian PCM bytes to f32 samples normalized to [-1.0, 1.0]
+// (still interleaved, see `analysis::channel::deinterleave`)
+pub fn binary_transformer(binary: Vec<u8>, audio_info: &AudioInfo) -> Vec<f32> {
+    let pcm_format = audio_info.pcm_format;
+    binary
+        .chunks_exact(pcm_format.bytes_per_sample())
+        .map(|chunk| match pcm_format {
+            SampleFormat::S16le => i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0,
+            // put the 3 bytes in the high bytes of an i32, then shift back to sign-extend
+            SampleFormat::S24le => {
+                (i32::from_le_bytes([0, chunk[0], chunk[1], chunk[2]]) >> 8) as f32 / 8388608.0
+            }
+            SampleFormat::S32le => {
+                i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f32 / 2147483648.0
+            }
+            SampleFormat::F32le => f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+        })
+        .collect()
 }
@@ -1,3 +1 @@
-pub mod app;
 pub mod handler;
-pub mod root;
@@ -1,14 +1,11 @@
-use crate::models::packet::WindowPacket;
-
-use super::app::AppError;
+use crate::models::packet::{AnalysisJob, PcmPacket, WindowPacket};
 use axum::http::StatusCode;
+use common::errors::{app::AppError, protocol::ProtocolError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum HandlerError {
-    #[error("UnexpectedMessageTypeError: unsupported message type received")]
-    UnexpectedMessageTypeError,
-    #[error("UnexpectedMessageError: {0}")]
-    UnexpectedMessageError(String),
+    #[error(transparent)]
+    ProtocolError(#[from] ProtocolError),
     #[error(transparent)]
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
@@ -16,38 +13,39 @@ pub enum HandlerError {
     #[error(transparent)]
     AxumError(#[from] axum::Error),
     #[error(transparent)]
-    TokioTungsteniteError(#[from] tokio_tungstenite::tungstenite::Error),
-    #[error(transparent)]
-    ParseIntError(#[from] std::num::ParseIntError),
+    TokioTungsteniteError(#[from] Box<tokio_tungstenite::tungstenite::Error>),
     #[error("AudioInfoError: {0}")]
     AudioInfoError(String),
     #[error(transparent)]
-    MpscVecU8SenderError(#[from] tokio::sync::mpsc::error::SendError<Vec<u8>>),
+    MpscPcmPacketTrySendError(#[from] tokio::sync::mpsc::error::TrySendError<PcmPacket>),
     #[error(transparent)]
     MpscWindowPacketSenderError(#[from] tokio::sync::mpsc::error::SendError<WindowPacket>),
     #[error(transparent)]
+    MpscAnalysisJobTrySendError(#[from] tokio::sync::mpsc::error::TrySendError<AnalysisJob>),
+    #[error(transparent)]
     TokioJoinError(#[from] tokio::task::JoinError),
     #[error(transparent)]
     RmpSerdeEncodeError(#[from] rmp_serde::encode::Error),
-    #[error("ParseAudioInfoError: Invalid audio info format: {0}")]
-    ParseAudioInfoError(String),
     #[error("AudioInfoUndefinedError: Audio info is not set")]
     AudioInfoUndefinedError,
+    #[error("TempoDetectorUnavailableError: {0}")]
+    TempoDetectorUnavailableError(String),
+    #[cfg(feature = "python")]
     #[error(transparent)]
     PyError(#[from] pyo3::PyErr),
 }
 
+// the tungstenite error is large, so it is boxed to keep every `Result` small
+impl From<tokio_tungstenite::tungstenite::Error> for HandlerError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        HandlerError::TokioTungsteniteError(Box::new(error))
+    }
+}
+
 impl From<HandlerError> for AppError {
     fn from(error: HandlerError) -> Self {
         match error {
-            HandlerError::UnexpectedMessageTypeError => AppError {
-                status_code: StatusCode::BAD_REQUEST,
-                message: "UnexpectedMessageTypeError: unsupported message type received".into(),
-            },
-            HandlerError::UnexpectedMessageError(e) => AppError {
-                status_code: StatusCode::BAD_REQUEST,
-                message: format!("UnexpectedMessageError: {e}"),
-            },
+            HandlerError::ProtocolError(e) => e.into(),
             HandlerError::SetGlobalDefaultError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("SetGlobalDefaultError: {e}"),
@@ -64,22 +62,22 @@ impl From<HandlerError> for AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("TokioTungsteniteError: {e}"),
             },
-            HandlerError::ParseIntError(e) => AppError {
-                status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("ParseIntError: {e}"),
-            },
             HandlerError::AudioInfoError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("AudioInfoError: {e}"),
             },
-            HandlerError::MpscVecU8SenderError(e) => AppError {
+            HandlerError::MpscPcmPacketTrySendError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("MpscVecU8SenderError: {e}"),
+                message: format!("MpscPcmPacketTrySendError: {e}"),
             },
             HandlerError::MpscWindowPacketSenderError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("MpscWindowPacketSenderError: {e}"),
             },
+            HandlerError::MpscAnalysisJobTrySendError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("MpscAnalysisJobTrySendError: {e}"),
+            },
             HandlerError::TokioJoinError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("TokioJoinError: {e}"),
@@ -88,14 +86,15 @@ impl From<HandlerError> for AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("RmpSerdeEncodeError: {e}"),
             },
-            HandlerError::ParseAudioInfoError(e) => AppError {
-                status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("ParseAudioInfoError: Invalid audio info format: {e}"),
-            },
             HandlerError::AudioInfoUndefinedError => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: "AudioInfoUndefinedError: Audio info is not set".into(),
             },
+            HandlerError::TempoDetectorUnavailableError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("TempoDetectorUnavailableError: {e}"),
+            },
+            #[cfg(feature = "python")]
             HandlerError::PyError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("PyError: {e}"),
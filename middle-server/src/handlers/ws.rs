@@ -3,38 +3,46 @@ use crate::{
         client_to_server::handle_client_to_server, pcm::pcm_data_processing,
         server_to_client::handle_server_to_client, window::window_data_processing,
     },
-    errors::{app::AppError, handler::HandlerError},
+    errors::handler::HandlerError,
     models::{
-        audio::{AudioInfo, RwLockAudioInfo},
-        packet::WindowPacket,
+        audio::RwLockAudioInfo,
+        config::Config,
+        options::RwLockAnalysisOptions,
+        packet::{MessagePack, PcmPacket, WindowPacket},
         shared_state::RwLockSharedState,
+        window::{PcmSettings, StreamLength},
         ws::MutexWebSocketClientWriter,
     },
 };
-use axum::extract::ws::WebSocket;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use axum::{
     extract::{State, WebSocketUpgrade},
     response::IntoResponse,
 };
-use futures_util::StreamExt;
+use common::errors::app::{AppError, close_code};
+use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::{sync::Mutex, task::JoinHandle};
 use tokio_tungstenite::connect_async;
 
 //* constant values *//
 static SERVER_URL: &str = "ws://localhost:5000";
-static WINDOW_SIZE: u64 = 200;
-static SLIDE_SIZE: u64 = 100;
+static WINDOW_LENGTH: StreamLength = StreamLength::Seconds(4.0);
+static HOP_LENGTH: StreamLength = StreamLength::Seconds(2.0);
+static LOUDNESS_INTERVAL: StreamLength = StreamLength::Seconds(0.1);
 static PCM_CHANNEL_CAPACITY: u64 = 1000;
 static WINDOW_CHANNEL_CAPACITY: u64 = 1000;
+static ANALYSIS_QUEUE_CAPACITY: u64 = 4;
+static DETECTOR_QUEUE_CAPACITY: u64 = 64;
 
 // handler
 pub async fn websocket_handler(
-    State(_shared_state): State<RwLockSharedState>, // unused shared state
+    State(shared_state): State<RwLockSharedState>,
     web_socket: WebSocketUpgrade,
 ) -> Result<impl IntoResponse, AppError> {
-    let response = web_socket.on_upgrade(|socket| async move {
-        if let Err(error) = websocket_processing(socket).await {
+    let config = shared_state.read().await.clone();
+    let response = web_socket.on_upgrade(move |socket| async move {
+        if let Err(error) = websocket_processing(socket, config).await {
             tracing::error!("WebSocket processing error: {:?}", error);
         }
         tracing::info!("WebSocket connection closed.");
@@ -43,11 +51,14 @@ pub async fn websocket_handler(
 }
 
 // websocket
-pub async fn websocket_processing(client_socket: WebSocket) -> Result<(), AppError> {
+pub async fn websocket_processing(
+    client_socket: WebSocket,
+    config: Config,
+) -> Result<(), AppError> {
     // connect to the server
     let (server_socket, _) = connect_async(SERVER_URL)
         .await
-        .map_err(HandlerError::TokioTungsteniteError)?;
+        .map_err(HandlerError::from)?;
     tracing::info!("Connection to server established.");
 
     // split client and server sockets
@@ -72,17 +83,23 @@ pub async fn websocket_processing(client_socket: WebSocket) -> Result<(), AppErr
     let shared_client_writer: MutexWebSocketClientWriter = Arc::new(Mutex::new(client_writer));
 
     // create tokio::sync::mpsc channel for streaming PCM data
-    let (pcm_tx, pcm_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(PCM_CHANNEL_CAPACITY as usize);
+    let (pcm_tx, pcm_rx) = tokio::sync::mpsc::channel::<PcmPacket>(PCM_CHANNEL_CAPACITY as usize);
     let (window_tx, window_rx) =
         tokio::sync::mpsc::channel::<WindowPacket>(WINDOW_CHANNEL_CAPACITY as usize);
 
     // create shared state for audio info
-    let shared_audio_info: RwLockAudioInfo =
-        Arc::new(tokio::sync::RwLock::new(AudioInfo::default()));
+    let shared_audio_info: RwLockAudioInfo = Arc::new(tokio::sync::RwLock::new(None));
+    // create shared state for the analysis options of the session
+    let shared_analysis_options: RwLockAnalysisOptions =
+        Arc::new(tokio::sync::RwLock::new(Default::default()));
 
     //* --- Start independent tasks --- *//
     // [task1] client -> server
-    let client_read_task = tokio::spawn(handle_client_to_server(client_reader, server_writer));
+    let client_read_task = tokio::spawn(handle_client_to_server(
+        client_reader,
+        server_writer,
+        Arc::clone(&shared_analysis_options),
+    ));
     // [task2] server -> client
     let server_read_task = tokio::spawn(handle_server_to_client(
         server_reader,
@@ -92,25 +109,85 @@ pub async fn websocket_processing(client_socket: WebSocket) -> Result<(), AppErr
     ));
     // [task3] pcm data processing
     let pcm_processing_task = tokio::spawn(pcm_data_processing(
-        WINDOW_SIZE,
-        SLIDE_SIZE,
+        PcmSettings {
+            window_length: WINDOW_LENGTH,
+            hop_length: HOP_LENGTH,
+            loudness_interval: LOUDNESS_INTERVAL,
+            detector_queue_capacity: DETECTOR_QUEUE_CAPACITY as usize,
+        },
+        config.clone(),
         pcm_rx,
         window_tx,
+        Arc::clone(&shared_client_writer),
+        Arc::clone(&shared_audio_info),
+        Arc::clone(&shared_analysis_options),
     ));
     // [task4] window data processing
     let window_processing_task = tokio::spawn(window_data_processing(
+        ANALYSIS_QUEUE_CAPACITY as usize,
+        config,
         window_rx,
         Arc::clone(&shared_client_writer),
         Arc::clone(&shared_audio_info),
+        Arc::clone(&shared_analysis_options),
     ));
 
-    //* When one of the tasks is completed, tokio make the other tasks also complete. *//
-    (tokio::select! {
-        response = client_read_task => response,
-        response = server_read_task => response,
-        response = pcm_processing_task => response,
-        response = window_processing_task => response,
-    })
-    .map_err(HandlerError::TokioJoinError)??;
+    let abort_handles = [
+        client_read_task.abort_handle(),
+        server_read_task.abort_handle(),
+        pcm_processing_task.abort_handle(),
+        window_processing_task.abort_handle(),
+    ];
+
+    //* The session ends when the client leaves, when the pipeline (task2 -> task3 -> task4) *//
+    //* drains after the end of the stream, or when any task fails. *//
+    let result = tokio::select! {
+        response = join_task(client_read_task) => response,
+        response = async {
+            tokio::try_join!(
+                join_task(server_read_task),
+                join_task(pcm_processing_task),
+                join_task(window_processing_task),
+            )
+            .map(|_| ())
+        } => response,
+    };
+    // stop the tasks that are still running
+    for abort_handle in abort_handles {
+        abort_handle.abort();
+    }
+
+    // tell the client why the session failed (best effort, the socket may already be gone)
+    if let Err(error) = result {
+        let error = AppError::from(error);
+        let message_pack = MessagePack::Error {
+            reason: error.message.clone(),
+        };
+        let mut writer = shared_client_writer.lock().await;
+        if let Ok(message) = message_pack.to_message() {
+            let _ = writer.send(message).await;
+        }
+        let _ = writer
+            .send(Message::Close(Some(CloseFrame {
+                code: error.close_code(),
+                reason: error.close_reason().into(),
+            })))
+            .await;
+        return Err(error);
+    }
+
+    // close the client connection (it may already be closed by the client)
+    let mut writer = shared_client_writer.lock().await;
+    let _ = writer
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::NORMAL,
+            reason: "end of stream".into(),
+        })))
+        .await;
     Ok(())
 }
+
+// wait for a task and flatten its join error into the task result
+async fn join_task(task: JoinHandle<Result<(), HandlerError>>) -> Result<(), HandlerError> {
+    task.await?
+}
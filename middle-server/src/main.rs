@@ -1,9 +1,11 @@
-use crate::{errors::root::RootError, handlers::ws::websocket_handler};
+use crate::{handlers::ws::websocket_handler, models::config::Config};
 use axum::{Router, extract::DefaultBodyLimit, routing::get};
+use common::errors::root::RootError;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
 
+pub mod analysis;
 pub mod applications;
 pub mod errors;
 pub mod handlers;
@@ -15,13 +17,16 @@ static PORT: u16 = 7000;
 
 #[tokio::main]
 async fn main() -> Result<(), RootError> {
-    // shared object
-    let shared_state = Arc::new(RwLock::new(0));
     // tracing
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
         .with_max_level(tracing::Level::DEBUG)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
+    // configuration
+    let config = Config::from_env()?;
+    tracing::info!("Default tempo detector: {}", config.tempo_detector);
+    // shared object
+    let shared_state = Arc::new(RwLock::new(config));
     // cors
     let cors = CorsLayer::new().allow_origin(tower_http::cors::Any);
 
@@ -1,5 +1,8 @@
 pub mod audio;
+pub mod config;
 pub mod delay;
+pub mod options;
 pub mod packet;
 pub mod shared_state;
+pub mod window;
 pub mod ws;
@@ -0,0 +1,104 @@
+use common::{errors::root::RootError, protocol::TempoDetectorKind};
+
+/// The environment variable that selects the default tempo detector.
+pub const TEMPO_DETECTOR_ENV: &str = "TEMPO_DETECTOR";
+/// The environment variable that names the Python tempo detector, as `module:function`.
+pub const TEMPO_DETECTOR_CALLABLE_ENV: &str = "TEMPO_DETECTOR_CALLABLE";
+/// The environment variable that replaces every tempo detector with a fixed tempo (in BPM),
+/// e.g. to test a client against a deterministic beat grid.
+pub const FIXED_TEMPO_ENV: &str = "FIXED_TEMPO";
+/// The environment variable that turns the harmonic/percussive separation on by default
+/// (`true` or `false`).
+pub const HPSS_ENV: &str = "HPSS";
+
+/// Server configuration, read once at startup.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The tempo detector of sessions that do not choose one.
+    pub tempo_detector: TempoDetectorKind,
+    /// The Python callable of the `python` tempo detector, as `module:function`.
+    pub tempo_detector_callable: Option<String>,
+    /// The tempo reported instead of running a tempo detector.
+    pub fixed_tempo: Option<f64>,
+    /// Whether sessions that do not choose separate the harmonic and percussive parts.
+    pub hpss: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // keep librosa as the default whenever it is available
+        #[cfg(feature = "python")]
+        let tempo_detector = TempoDetectorKind::Librosa;
+        #[cfg(not(feature = "python"))]
+        let tempo_detector = TempoDetectorKind::Native;
+        Config {
+            tempo_detector,
+            tempo_detector_callable: None,
+            fixed_tempo: None,
+            hpss: false,
+        }
+    }
+}
+
+impl Config {
+    /// Read the configuration from the environment, using the defaults for unset variables.
+    pub fn from_env() -> Result<Self, RootError> {
+        let mut config = Config::default();
+        if let Ok(value) = std::env::var(TEMPO_DETECTOR_ENV) {
+            config.tempo_detector = TempoDetectorKind::ALL
+                .into_iter()
+                .find(|kind| kind.as_str() == value.to_ascii_lowercase())
+                .ok_or_else(|| {
+                    RootError::ConfigError(format!(
+                        "unknown tempo detector: {value} (expected librosa, native or python)"
+                    ))
+                })?;
+        }
+        config.tempo_detector_callable = std::env::var(TEMPO_DETECTOR_CALLABLE_ENV).ok();
+        if let Ok(value) = std::env::var(FIXED_TEMPO_ENV) {
+            let bpm = value
+                .parse::<f64>()
+                .ok()
+                .filter(|bpm| *bpm > 0.0)
+                .ok_or_else(|| {
+                    RootError::ConfigError(format!(
+                        "{FIXED_TEMPO_ENV} must be a positive number, got {value}"
+                    ))
+                })?;
+            config.fixed_tempo = Some(bpm);
+        }
+        if let Ok(value) = std::env::var(HPSS_ENV) {
+            config.hpss = value.to_ascii_lowercase().parse::<bool>().map_err(|_| {
+                RootError::ConfigError(format!("{HPSS_ENV} must be true or false, got {value}"))
+            })?;
+        }
+        config
+            .check_tempo_detector(config.tempo_detector)
+            .map_err(RootError::ConfigError)?;
+        Ok(config)
+    }
+
+    /// Check that `kind` can be used with this build and configuration.
+    pub fn check_tempo_detector(&self, kind: TempoDetectorKind) -> Result<(), String> {
+        match kind {
+            TempoDetectorKind::Native => Ok(()),
+            TempoDetectorKind::Librosa | TempoDetectorKind::Python
+                if cfg!(not(feature = "python")) =>
+            {
+                Err(format!(
+                    "the {kind} tempo detector needs middle-server built with the python feature"
+                ))
+            }
+            TempoDetectorKind::Librosa => Ok(()),
+            TempoDetectorKind::Python => match &self.tempo_detector_callable {
+                Some(callable) if callable.split_once(':').is_some() => Ok(()),
+                Some(callable) => Err(format!(
+                    "{TEMPO_DETECTOR_CALLABLE_ENV} must be module:function, got {callable}"
+                )),
+                None => Err(format!(
+                    "the python tempo detector needs {TEMPO_DETECTOR_CALLABLE_ENV} to be set"
+                )),
+            },
+        }
+    }
+}
@@ -0,0 +1,4 @@
+use common::protocol::AnalysisOptions;
+
+/// The analysis options of the session, set when the client opens a track.
+pub type RwLockAnalysisOptions = std::sync::Arc<tokio::sync::RwLock<AnalysisOptions>>;
@@ -1,9 +1,373 @@
+use crate::analysis::{
+    chord::ChordSegment,
+    groove::{BarGroove, GrooveSummary},
+    key::KeyEstimate,
+    meter::Meter,
+    onset::Instrument,
+    segment::{Section, SectionChange, SectionLabel},
+};
+use axum::extract::ws::Message;
+use common::audio::AudioInfo;
 use serde::Serialize;
 
-pub struct WindowPacket(pub Vec<u8>);
+/// PCM data received from the server.
+///
+/// Frames are counted from the start of the stream, so `end_frame` is exclusive.
+#[derive(Debug)]
+pub struct PcmPacket {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    pub binary: Vec<u8>,
+}
+
+/// A window of PCM data to analyze, covering the frames `start_frame..end_frame`.
+#[derive(Debug)]
+pub struct WindowPacket {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    /// The frames from the start of this window to the start of the next one.
+    pub hop_frames: u64,
+    pub binary: Vec<u8>,
+    /// The frames of the onsets the stream detectors found in this window.
+    pub onset_frames: Vec<u64>,
+}
+
+/// A window handed to the analysis executor, with the format of its PCM data.
+#[derive(Debug)]
+pub struct AnalysisJob {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    /// The frames from the start of this window to the start of the next one.
+    pub hop_frames: u64,
+    pub binary: Vec<u8>,
+    /// The frames of the onsets the stream detectors found in this window.
+    pub onset_frames: Vec<u64>,
+    pub audio_info: AudioInfo,
+}
+
+impl AnalysisJob {
+    /// The end of the part of the window that is reported: the middle of its overlap with the
+    /// next window, so every frame is reported by the window that sees the most around it.
+    pub fn interior_end_frame(&self) -> u64 {
+        let overlap = (self.end_frame - self.start_frame).saturating_sub(self.hop_frames);
+        self.end_frame - overlap / 2
+    }
+}
 
+/// A message sent from the middle-server to the client, encoded as a MessagePack binary frame.
+///
+/// The `type` field tells the client which variant it received, e.g.
+/// `{"type": "analysis", "start_frame": 0, "end_frame": 88200, "bpm": 120.0}`.
 #[derive(Debug, Serialize)]
-pub struct MessagePack {
-    pub pcm: Vec<u8>,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessagePack {
+    /// Answer to the hello message of the client.
+    Hello { version: u16 },
+    /// The format of the PCM data in the following audio chunks.
+    StreamHeader(AudioInfo),
+    /// PCM data to play, forwarded as soon as it arrives from the server.
+    AudioChunk {
+        start_frame: u64,
+        #[serde(with = "serde_bytes")]
+        pcm: Vec<u8>,
+    },
+    /// The levels of the stream, sent at a fixed rate along with the audio chunks.
+    Loudness(LoudnessResult),
+    /// The band levels of the stream, sent at the spectrum rate of the session.
+    ///
+    /// The bands are log-spaced from 20 Hz to 20 kHz (or the Nyquist frequency),
+    /// and each level is a byte from 0 (-100 dB or below) to 255 (0 dB).
+    Spectrum {
+        /// The frame after the last frame of the analyzed samples.
+        frame: u64,
+        #[serde(with = "serde_bytes")]
+        bands: Vec<u8>,
+    },
+    /// A percussive hit, sent as soon as it is detected (a few milliseconds after it happens).
+    Onset(OnsetResult),
+    /// The result of analyzing the PCM data, sent once the analysis of a window is done.
+    Analysis(AnalysisResult),
+    /// The pitch of the stream at a fixed hop, sent for every chunk.
+    Pitch(PitchResult),
+    /// The start of a new section, sent with the analysis that found it
+    /// (about 5 s after the boundary).
+    SectionChange(SectionResult),
+    /// The analysis of the whole track, sent once after the last analysis.
+    Summary(TrackSummary),
+    /// The session failed; the connection is closed after this message.
+    Error { reason: String },
+    /// The whole stream has been forwarded and analyzed.
+    EndOfStream,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalysisResult {
+    /// The first frame of the analyzed window.
+    pub start_frame: u64,
+    /// The frame after the last frame of the analyzed window.
+    pub end_frame: u64,
+    /// The tracked tempo in BPM, `None` if there is no steady tempo (e.g. silence).
+    pub bpm: Option<f64>,
+    /// How much the recent windows agree on the tempo, from 0.0 to 1.0.
+    pub confidence: f64,
+    /// The beats of the window that were not reported by an earlier window.
+    pub beats: Vec<Beat>,
+    /// The local tempo at each of `beats` (except where the curve starts over).
+    pub tempo_curve: Vec<TempoCurvePoint>,
+    /// The time signature of the stream so far, `None` until enough beats have been seen.
+    pub meter: Option<Meter>,
+    /// The key of the window, `None` if it has no pitched content.
+    pub key: Option<KeyResult>,
+    /// The chords from the end of the previous window on. The first chord continues the last
+    /// chord of the previous window if it has the same `start_frame`.
+    pub chords: Vec<ChordResult>,
+    /// The harmonic and percussive parts of the window, `None` unless the session separates them.
+    pub hpss: Option<HpssResult>,
+    /// The channels of the window, `None` for a silent window.
+    pub stereo: Option<StereoResult>,
+    /// The groove of the bars that ended in the window.
+    pub grooves: Vec<GrooveResult>,
+}
+
+/// The swing and the micro-timing of a bar.
+#[derive(Debug, Serialize)]
+pub struct GrooveResult {
+    /// The bar, counted from the first bar of the stream.
+    pub bar: u64,
+    /// The frame of the first beat of the bar, and of the first beat of the next bar.
+    pub start_frame: u64,
+    pub end_frame: u64,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// The length of the on-beat note over the length of the off-beat note: 1.0 straight,
+    /// 2.0 triplet swing. `None` if the bar has no off-beats.
+    pub swing: Option<f32>,
+    /// The note value the swing is measured on, 8 or 16.
+    pub subdivision: u32,
+    /// How far the nearest onset is from each sixteenth note of the bar in milliseconds
+    /// (positive is late, `None` without an onset).
+    pub deviations: Vec<Option<f32>>,
+}
+
+impl GrooveResult {
+    pub fn new(groove: BarGroove, sample_rate: u32) -> Self {
+        GrooveResult {
+            bar: groove.bar,
+            start_frame: (groove.start * sample_rate as f64).round() as u64,
+            end_frame: (groove.end * sample_rate as f64).round() as u64,
+            start_time: groove.start,
+            end_time: groove.end,
+            swing: groove.swing,
+            subdivision: groove.subdivision,
+            deviations: groove.deviations,
+        }
+    }
+}
+
+/// The channels of a window.
+#[derive(Debug, Serialize)]
+pub struct StereoResult {
+    /// The RMS level of each channel in dBFS (at least -120).
+    pub levels: Vec<f32>,
+    /// The correlation of the left and right channels, from -1.0 to 1.0.
+    /// Negative values are phase problems that cancel out in mono. `None` for a mono stream.
+    pub correlation: Option<f32>,
+    /// The share of the side signal in the energy, from 0.0 (mono) to 1.0 (out of phase).
+    /// `None` for a mono stream.
+    pub width: Option<f32>,
+}
+
+/// The harmonic (pads, vocals, bass) and percussive (drums) parts of a window.
+#[derive(Debug, Serialize)]
+pub struct HpssResult {
+    /// The RMS level of the harmonic part in dBFS (at least -120).
+    pub harmonic_level: f32,
+    /// The RMS level of the percussive part in dBFS (at least -120).
+    pub percussive_level: f32,
+    /// The share of the percussive part in the energy of the window, from 0.0 to 1.0.
+    pub percussive_ratio: f32,
+}
+
+/// A beat on the stream timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct Beat {
+    /// The frame of the beat, counted from the start of the stream.
+    pub frame: u64,
+    /// The time of the beat in seconds from the start of the stream.
+    pub time: f64,
+    /// Whether the beat is the first beat of a bar.
+    pub downbeat: bool,
+    /// The bar of the beat, counted from 0 (bar 0 may be a partial bar).
+    pub bar: u64,
+    /// The beat within the bar, counted from 0 (0 is the downbeat).
+    pub beat_in_bar: u32,
+}
+
+/// The local tempo at a beat, from the intervals of the beats around it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TempoCurvePoint {
+    /// The frame of the beat, counted from the start of the stream.
+    pub frame: u64,
+    /// The time of the beat in seconds from the start of the stream.
+    pub time: f64,
     pub bpm: f64,
 }
+
+/// An onset on the stream timeline.
+#[derive(Debug, Serialize)]
+pub struct OnsetResult {
+    /// The frame of the onset, counted from the start of the stream.
+    pub frame: u64,
+    /// The time of the onset in seconds from the start of the stream.
+    pub time: f64,
+    /// The strength of the onset relative to the recent hits, from 0.0 to 1.0.
+    pub strength: f32,
+    /// The instrument guessed from the frequency bands of the onset.
+    pub instrument: Instrument,
+}
+
+/// The pitch frames of a chunk. Frame `i` is centered on `start_frame + i * hop_frames`.
+#[derive(Debug, Serialize)]
+pub struct PitchResult {
+    pub start_frame: u64,
+    /// The time of `start_frame` in seconds from the start of the stream.
+    pub start_time: f64,
+    /// The frames between two pitch frames (10 ms).
+    pub hop_frames: u32,
+    /// The most likely f0 of each frame in Hz, 0.0 if no period stands out at all.
+    pub f0: Vec<f32>,
+    /// The probability that each frame is voiced, from 0.0 to 1.0.
+    pub voicing: Vec<f32>,
+}
+
+/// The levels of the frames `start_frame..end_frame`. Levels are in dB (at least -120).
+#[derive(Debug, Serialize)]
+pub struct LoudnessResult {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    /// The RMS of each channel in dBFS.
+    pub rms: Vec<f32>,
+    /// The largest sample of each channel in dBFS.
+    pub sample_peak: Vec<f32>,
+    /// The true peak (4x oversampled) of each channel in dBTP.
+    pub true_peak: Vec<f32>,
+    /// The momentary loudness (400 ms) in LUFS.
+    pub momentary: Option<f32>,
+    /// The short-term loudness (3 s) in LUFS.
+    pub short_term: Option<f32>,
+    /// Whether any sample reached full scale.
+    pub clipping: bool,
+}
+
+/// A musical key.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyResult {
+    /// e.g. `A minor`
+    pub name: String,
+    /// The key in Camelot notation, e.g. `8A`.
+    pub camelot: String,
+    /// How well the chroma matches the key profile, from 0.0 to 1.0.
+    pub confidence: f64,
+}
+
+impl From<KeyEstimate> for KeyResult {
+    fn from(estimate: KeyEstimate) -> Self {
+        KeyResult {
+            name: estimate.key.to_string(),
+            camelot: estimate.key.camelot(),
+            confidence: estimate.correlation.clamp(0.0, 1.0) as f64,
+        }
+    }
+}
+
+/// A chord on the stream timeline, covering the frames `start_frame..end_frame`.
+#[derive(Debug, Serialize)]
+pub struct ChordResult {
+    pub start_frame: u64,
+    pub end_frame: u64,
+    /// The time of `start_frame` in seconds from the start of the stream.
+    pub start_time: f64,
+    /// The time of `end_frame` in seconds from the start of the stream.
+    pub end_time: f64,
+    /// e.g. `C`, `F#m`, `None` if there is no chord (e.g. silence or drums only).
+    pub name: Option<String>,
+    /// How well the chroma matches the chord, from 0.0 to 1.0.
+    pub confidence: f32,
+}
+
+impl ChordResult {
+    pub fn new(segment: ChordSegment, sample_rate: u32) -> Self {
+        ChordResult {
+            start_frame: segment.start_frame,
+            end_frame: segment.end_frame,
+            start_time: segment.start_frame as f64 / sample_rate as f64,
+            end_time: segment.end_frame as f64 / sample_rate as f64,
+            name: segment.chord.map(|chord| chord.to_string()),
+            confidence: segment.confidence.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// A section of the track. A section change has no end yet.
+#[derive(Debug, Serialize)]
+pub struct SectionResult {
+    /// The first frame of the section, counted from the start of the stream.
+    pub start_frame: u64,
+    /// The time of the first frame in seconds from the start of the stream.
+    pub start_time: f64,
+    /// The frame after the last frame of the section.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_frame: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<f64>,
+    /// A guess of what kind of section it is.
+    pub label: SectionLabel,
+    /// How much the level and timbre change at the start of the section, from 0.0 to 1.0.
+    pub confidence: f32,
+}
+
+impl SectionResult {
+    pub fn from_change(change: SectionChange, sample_rate: u32) -> Self {
+        SectionResult {
+            start_frame: change.frame,
+            start_time: change.frame as f64 / sample_rate as f64,
+            end_frame: None,
+            end_time: None,
+            label: change.label,
+            confidence: change.confidence,
+        }
+    }
+
+    pub fn from_section(section: Section, sample_rate: u32) -> Self {
+        SectionResult {
+            start_frame: section.start_frame,
+            start_time: section.start_frame as f64 / sample_rate as f64,
+            end_frame: Some(section.end_frame),
+            end_time: Some(section.end_frame as f64 / sample_rate as f64),
+            label: section.label,
+            confidence: section.confidence,
+        }
+    }
+}
+
+/// The analysis of the whole track.
+#[derive(Debug, Serialize)]
+pub struct TrackSummary {
+    /// The key of the whole track, `None` if it has no pitched content.
+    pub key: Option<KeyResult>,
+    /// The time signature of the whole track, `None` if it has too few beats.
+    pub meter: Option<Meter>,
+    /// The sections of the whole track, in order.
+    pub sections: Vec<SectionResult>,
+    /// The tempo curve of the whole track, in order.
+    pub tempo_curve: Vec<TempoCurvePoint>,
+    /// The groove of the whole track, `None` if no bar was measured.
+    pub groove: Option<GrooveSummary>,
+}
+
+impl MessagePack {
+    /// Encode the message as a binary WebSocket frame.
+    pub fn to_message(&self) -> Result<Message, rmp_serde::encode::Error> {
+        Ok(Message::Binary(rmp_serde::to_vec_named(self)?.into()))
+    }
+}
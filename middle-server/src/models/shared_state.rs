@@ -1 +1,3 @@
-pub type RwLockSharedState = std::sync::Arc<tokio::sync::RwLock<i32>>;
+use crate::models::config::Config;
+
+pub type RwLockSharedState = std::sync::Arc<tokio::sync::RwLock<Config>>;
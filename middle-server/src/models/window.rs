@@ -0,0 +1,30 @@
+/// A length along the stream, given either in seconds or in frames.
+#[derive(Debug, Clone, Copy)]
+pub enum StreamLength {
+    Seconds(f64),
+    Frames(u64),
+}
+
+impl StreamLength {
+    /// The length in frames at `sample_rate` (at least one frame).
+    pub fn to_frames(self, sample_rate: u32) -> u64 {
+        let frames = match self {
+            StreamLength::Seconds(seconds) => (seconds * sample_rate as f64).round() as u64,
+            StreamLength::Frames(frames) => frames,
+        };
+        frames.max(1)
+    }
+}
+
+/// How the PCM stream of a session is cut into windows and followed on the way.
+#[derive(Debug, Clone, Copy)]
+pub struct PcmSettings {
+    /// The length of every analysis window.
+    pub window_length: StreamLength,
+    /// The distance from the start of one window to the start of the next one.
+    pub hop_length: StreamLength,
+    /// The length of one loudness reading.
+    pub loudness_interval: StreamLength,
+    /// How many packets (and results) the queues of the stream detectors hold.
+    pub detector_queue_capacity: usize,
+}
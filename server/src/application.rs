@@ -1,2 +1,3 @@
 pub mod analyzer;
+pub mod decoder;
 pub mod streamer;
@@ -1,18 +1,21 @@
-use crate::{errors::analyzer::AnalyzerError, models::audio::AudioInfo};
+use crate::{application::decoder::open_decoder, errors::analyzer::AnalyzerError};
+use common::audio::AudioInfo;
+use std::path::Path;
 
-pub fn wave_analyzer() -> Result<AudioInfo, AnalyzerError> {
-    // read wav file
-    let reader = hound::WavReader::open("data/sample3.wav")?;
+pub fn wave_analyzer(path: &Path) -> Result<AudioInfo, AnalyzerError> {
+    // open audio file
+    let decoder = open_decoder(path)?;
 
     // get headers
-    let spec = reader.spec();
+    let audio_info = decoder.audio_info();
     tracing::info!(
-        "WAV: {}Hz, {}ch, {}bits, {:?}",
-        spec.sample_rate,
-        spec.channels,
-        spec.bits_per_sample,
-        spec.sample_format
+        "{:?}: {}Hz, {}ch, {}bits, {}",
+        path,
+        audio_info.sample_rate,
+        audio_info.channels,
+        audio_info.bits_per_sample,
+        audio_info.pcm_format
     );
 
-    Ok(spec.into())
+    Ok(audio_info)
 }
@@ -0,0 +1,243 @@
+use crate::errors::decoder::DecoderError;
+use common::audio::{AudioInfo, SampleFormat};
+use std::{collections::VecDeque, fs::File, io::BufReader, path::Path};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{CODEC_TYPE_NULL, DecoderOptions},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// File extensions that `open_decoder` can decode.
+pub const SUPPORTED_EXTENSIONS: [&str; 8] =
+    ["wav", "flac", "mp3", "ogg", "oga", "m4a", "mp4", "aac"];
+
+/// A source of interleaved little-endian PCM chunks.
+pub trait AudioDecoder: Send {
+    /// The format of the PCM chunks returned by `next_chunk`.
+    fn audio_info(&self) -> AudioInfo;
+
+    /// Decode up to `frames` frames.
+    ///
+    /// Returns `None` once the end of the stream is reached.
+    fn next_chunk(&mut self, frames: usize) -> Result<Option<Vec<u8>>, DecoderError>;
+}
+
+/// Whether `open_decoder` can decode the file at `path`, judging by its extension.
+pub fn is_supported(path: &Path) -> bool {
+    extension(path).is_some_and(|extension| SUPPORTED_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Open a decoder for the file at `path`.
+///
+/// WAV files are read with hound, every other format with symphonia.
+pub fn open_decoder(path: &Path) -> Result<Box<dyn AudioDecoder>, DecoderError> {
+    match extension(path).as_deref() {
+        Some("wav") => Ok(Box::new(WavDecoder::open(path)?)),
+        _ => Ok(Box::new(SymphoniaDecoder::open(path)?)),
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+}
+
+// WAV (hound)
+pub struct WavDecoder {
+    reader: hound::WavReader<BufReader<File>>,
+    pcm_format: SampleFormat,
+    /// Left shift that scales an integer sample up to `pcm_format`.
+    ///
+    /// e.g. 8-bit samples are sent as s16le, so they are shifted by 8 bits.
+    shift: u16,
+}
+
+impl WavDecoder {
+    pub fn open(path: &Path) -> Result<Self, DecoderError> {
+        let reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        let pcm_format = match (spec.sample_format, spec.bits_per_sample) {
+            (hound::SampleFormat::Float, 32) => SampleFormat::F32le,
+            (hound::SampleFormat::Int, 1..=16) => SampleFormat::S16le,
+            (hound::SampleFormat::Int, 17..=24) => SampleFormat::S24le,
+            (hound::SampleFormat::Int, 25..=32) => SampleFormat::S32le,
+            _ => return Err(hound::Error::Unsupported.into()),
+        };
+        let shift = match pcm_format {
+            SampleFormat::F32le => 0,
+            _ => pcm_format.bits_per_sample() - spec.bits_per_sample,
+        };
+        Ok(WavDecoder {
+            reader,
+            pcm_format,
+            shift,
+        })
+    }
+}
+
+impl AudioDecoder for WavDecoder {
+    fn audio_info(&self) -> AudioInfo {
+        let spec = self.reader.spec();
+        AudioInfo::new(spec.channels, spec.sample_rate, self.pcm_format)
+    }
+
+    fn next_chunk(&mut self, frames: usize) -> Result<Option<Vec<u8>>, DecoderError> {
+        let samples_per_chunk = frames * self.reader.spec().channels as usize;
+        let mut buf = Vec::with_capacity(samples_per_chunk * self.pcm_format.bytes_per_sample());
+
+        // the samples iterator continues from the current position of the reader
+        match self.pcm_format {
+            SampleFormat::F32le => {
+                for sample in self.reader.samples::<f32>().take(samples_per_chunk) {
+                    buf.extend_from_slice(&sample?.to_le_bytes());
+                }
+            }
+            pcm_format => {
+                let bytes_per_sample = pcm_format.bytes_per_sample();
+                for sample in self.reader.samples::<i32>().take(samples_per_chunk) {
+                    let sample = sample? << self.shift;
+                    // little-endian, so the low bytes carry the 16 or 24 bit sample
+                    buf.extend_from_slice(&sample.to_le_bytes()[..bytes_per_sample]);
+                }
+            }
+        }
+
+        Ok((!buf.is_empty()).then_some(buf))
+    }
+}
+
+// FLAC, MP3, Ogg Vorbis, AAC (symphonia)
+pub struct SymphoniaDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn symphonia::core::codecs::Decoder>,
+    track_id: u32,
+    channels: u16,
+    sample_rate: u32,
+    /// Decoded interleaved samples that have not been returned yet.
+    pending: VecDeque<f32>,
+    finished: bool,
+}
+
+impl SymphoniaDecoder {
+    pub fn open(path: &Path) -> Result<Self, DecoderError> {
+        let file = File::open(path)?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        // give the probe a hint from the file extension
+        let mut hint = Hint::new();
+        if let Some(extension) = extension(path) {
+            hint.with_extension(&extension);
+        }
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+        let format = probed.format;
+
+        // pick the first decodable audio track
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| DecoderError::NoAudioTrackError(path.display().to_string()))?;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+        let track_id = track.id;
+        let channels = track
+            .codec_params
+            .channels
+            .map(|channels| channels.count() as u16)
+            .unwrap_or(0);
+        let sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+
+        let mut decoder = SymphoniaDecoder {
+            format,
+            decoder,
+            track_id,
+            channels,
+            sample_rate,
+            pending: VecDeque::new(),
+            finished: false,
+        };
+
+        // some containers only reveal the signal spec once the first packet is decoded
+        while (decoder.channels == 0 || decoder.sample_rate == 0) && !decoder.finished {
+            decoder.decode_packet()?;
+        }
+        if decoder.channels == 0 || decoder.sample_rate == 0 {
+            return Err(DecoderError::UnknownSignalSpecError(
+                path.display().to_string(),
+            ));
+        }
+
+        Ok(decoder)
+    }
+
+    /// Decode the next packet of the selected track into `pending`.
+    fn decode_packet(&mut self) -> Result<(), DecoderError> {
+        let packet = match self.format.next_packet() {
+            Ok(packet) => packet,
+            // end of stream
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                self.finished = true;
+                return Ok(());
+            }
+            // chained streams are not supported, so stop at the first one
+            Err(SymphoniaError::ResetRequired) => {
+                self.finished = true;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != self.track_id {
+            return Ok(());
+        }
+
+        match self.decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                self.channels = spec.channels.count() as u16;
+                self.sample_rate = spec.rate;
+
+                let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                self.pending.extend(buffer.samples());
+            }
+            // a corrupted packet is skipped rather than aborting the stream
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::warn!("Skipping undecodable packet: {}", e);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+}
+
+impl AudioDecoder for SymphoniaDecoder {
+    fn audio_info(&self) -> AudioInfo {
+        // compressed codecs decode to float, so no precision is lost
+        AudioInfo::new(self.channels, self.sample_rate, SampleFormat::F32le)
+    }
+
+    fn next_chunk(&mut self, frames: usize) -> Result<Option<Vec<u8>>, DecoderError> {
+        let samples_per_chunk = frames * self.channels as usize;
+        while self.pending.len() < samples_per_chunk && !self.finished {
+            self.decode_packet()?;
+        }
+
+        let length = samples_per_chunk.min(self.pending.len());
+        let mut buf = Vec::with_capacity(length * SampleFormat::F32le.bytes_per_sample());
+        for sample in self.pending.drain(..length) {
+            buf.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        Ok((!buf.is_empty()).then_some(buf))
+    }
+}
@@ -1,46 +1,34 @@
-use crate::errors::streamer::StreamerError;
+use crate::{application::decoder::open_decoder, errors::streamer::StreamerError};
 use axum::extract::ws::WebSocket;
+use std::path::Path;
 
-pub async fn wave_streamer(socket: &mut WebSocket) -> Result<(), StreamerError> {
-    // read wav file
-    let mut reader = hound::WavReader::open("data/sample3.wav")?;
+pub async fn wave_streamer(socket: &mut WebSocket, path: &Path) -> Result<(), StreamerError> {
+    // open audio file
+    let mut decoder = open_decoder(path)?;
     // get headers
-    let spec = reader.spec();
+    let audio_info = decoder.audio_info();
     tracing::info!(
-        "WAV: {}Hz, {}ch, {}bits, {:?}",
-        spec.sample_rate,
-        spec.channels,
-        spec.bits_per_sample,
-        spec.sample_format
+        "{:?}: {}Hz, {}ch, {}bits, {}",
+        path,
+        audio_info.sample_rate,
+        audio_info.channels,
+        audio_info.bits_per_sample,
+        audio_info.pcm_format
     );
     // get body (PCM samples)
-    let mut samples = reader.samples::<i16>();
     let frames_per_chunk = 1024;
-    let samples_per_chunk = frames_per_chunk * spec.channels as usize;
     // define interval
-    let interval =
-        tokio::time::Duration::from_secs_f64(frames_per_chunk as f64 / spec.sample_rate as f64);
+    let interval = tokio::time::Duration::from_secs_f64(
+        frames_per_chunk as f64 / audio_info.sample_rate as f64,
+    );
 
     // send PCM data to middle-server
-    loop {
-        let mut buf = Vec::with_capacity(samples_per_chunk);
-
-        // send chunks of a sample
-        for _ in 0..samples_per_chunk {
-            if let Some(Ok(sample)) = samples.next() {
-                buf.extend_from_slice(&sample.to_le_bytes());
-            }
-        }
-
-        // break point
-        if buf.is_empty() {
-            break;
-        }
-
+    // break point: the decoder reached the end of the stream
+    while let Some(buf) = decoder.next_chunk(frames_per_chunk)? {
         // send PCM data
         /*
-            binary size = frames_per_chunk × spec.channels × (spec.bits_per_sample / 8)
-            NOTE: (spec.bits_per_sample / 8) -> bit size to byte size conversion
+            binary size = frames_per_chunk × channels × (bits_per_sample / 8)
+            NOTE: (bits_per_sample / 8) -> bit size to byte size conversion
             e.g. 1024 frames × 2 channels × (16 bits / 8) = 4096 bytes
             e.g. 1024 frames × 1 channel × (16 bits / 8) = 2048 bytes
         */
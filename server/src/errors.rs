@@ -1,5 +1,4 @@
 pub mod analyzer;
-pub mod app;
+pub mod decoder;
 pub mod handler;
-pub mod root;
 pub mod streamer;
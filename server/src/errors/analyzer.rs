@@ -1,19 +1,16 @@
-use super::app::AppError;
-use axum::http::StatusCode;
+use super::decoder::DecoderError;
+use common::errors::app::AppError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AnalyzerError {
     #[error(transparent)]
-    HoundError(#[from] hound::Error),
+    DecoderError(#[from] DecoderError),
 }
 
 impl From<AnalyzerError> for AppError {
     fn from(error: AnalyzerError) -> Self {
         match error {
-            AnalyzerError::HoundError(e) => AppError {
-                status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("HoundError: {e}"),
-            },
+            AnalyzerError::DecoderError(e) => e.into(),
         }
     }
 }
@@ -0,0 +1,45 @@
+use axum::http::StatusCode;
+use common::errors::app::AppError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecoderError {
+    #[error(transparent)]
+    HoundError(#[from] hound::Error),
+    #[error(transparent)]
+    SymphoniaError(#[from] symphonia::core::errors::Error),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("NoAudioTrackError: no decodable audio track in {0}")]
+    NoAudioTrackError(String),
+    #[error("UnknownSignalSpecError: channels or sample rate of {0} is unknown")]
+    UnknownSignalSpecError(String),
+}
+
+impl From<DecoderError> for AppError {
+    fn from(error: DecoderError) -> Self {
+        match error {
+            DecoderError::HoundError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("HoundError: {e}"),
+            },
+            DecoderError::SymphoniaError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("SymphoniaError: {e}"),
+            },
+            DecoderError::IoError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("IoError: {e}"),
+            },
+            DecoderError::NoAudioTrackError(e) => AppError {
+                status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                message: format!("NoAudioTrackError: no decodable audio track in {e}"),
+            },
+            DecoderError::UnknownSignalSpecError(e) => AppError {
+                status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                message: format!(
+                    "UnknownSignalSpecError: channels or sample rate of {e} is unknown"
+                ),
+            },
+        }
+    }
+}
@@ -1,12 +1,12 @@
-use super::app::AppError;
 use axum::http::StatusCode;
+use common::errors::{app::AppError, protocol::ProtocolError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum HandlerError {
-    #[error("UnexpectedMessageTypeError: unsupported message type received")]
-    UnexpectedMessageTypeError,
-    #[error("UnexpectedMessageError: {0}")]
-    UnexpectedMessageError(String),
+    #[error(transparent)]
+    ProtocolError(#[from] ProtocolError),
+    #[error("TrackNotFoundError: no track with id {0:?}")]
+    TrackNotFoundError(String),
     #[error(transparent)]
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
@@ -18,13 +18,10 @@ pub enum HandlerError {
 impl From<HandlerError> for AppError {
     fn from(error: HandlerError) -> Self {
         match error {
-            HandlerError::UnexpectedMessageTypeError => AppError {
-                status_code: StatusCode::BAD_REQUEST,
-                message: "UnexpectedMessageTypeError: unsupported message type received".into(),
-            },
-            HandlerError::UnexpectedMessageError(e) => AppError {
-                status_code: StatusCode::BAD_REQUEST,
-                message: format!("UnexpectedMessageError: {e}"),
+            HandlerError::ProtocolError(e) => e.into(),
+            HandlerError::TrackNotFoundError(e) => AppError {
+                status_code: StatusCode::NOT_FOUND,
+                message: format!("TrackNotFoundError: no track with id {e:?}"),
             },
             HandlerError::SetGlobalDefaultError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
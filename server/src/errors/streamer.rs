@@ -1,10 +1,11 @@
-use super::app::AppError;
+use super::decoder::DecoderError;
 use axum::http::StatusCode;
+use common::errors::app::AppError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum StreamerError {
     #[error(transparent)]
-    HoundError(#[from] hound::Error),
+    DecoderError(#[from] DecoderError),
     #[error(transparent)]
     AxumError(#[from] axum::Error),
 }
@@ -12,10 +13,7 @@ pub enum StreamerError {
 impl From<StreamerError> for AppError {
     fn from(error: StreamerError) -> Self {
         match error {
-            StreamerError::HoundError(e) => AppError {
-                status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("HoundError: {e}"),
-            },
+            StreamerError::DecoderError(e) => e.into(),
             StreamerError::AxumError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("AxumError: {e}"),
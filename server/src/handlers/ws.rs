@@ -1,71 +1,135 @@
 use crate::{
     application::streamer::wave_streamer,
-    errors::{app::AppError, handler::HandlerError},
-    models::shared_state::RwLockSharedState,
+    errors::handler::HandlerError,
+    models::{shared_state::RwLockSharedState, track::Track},
 };
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use axum::{
     extract::{State, WebSocketUpgrade},
     response::IntoResponse,
 };
+use common::{
+    errors::{app::AppError, protocol::ProtocolError},
+    protocol::{ControlFrame, ControlMessage},
+};
 
 // handler
 pub async fn websocket_handler(
     State(shared_state): State<RwLockSharedState>,
     web_socket: WebSocketUpgrade,
 ) -> Result<impl IntoResponse, AppError> {
-    let shared_state = shared_state.read().await;
     let response = web_socket.on_upgrade(|socket| async move {
-        if let Err(error) = websocket_processing(socket).await {
+        if let Err(error) = websocket_processing(socket, shared_state).await {
             tracing::error!("WebSocket error: {:?}", error);
         }
     });
-    drop(shared_state);
     Ok(response)
 }
 
 //websocket
-pub async fn websocket_processing(mut socket: WebSocket) -> Result<(), AppError> {
+pub async fn websocket_processing(
+    mut socket: WebSocket,
+    shared_state: RwLockSharedState,
+) -> Result<(), AppError> {
+    let result = session_processing(&mut socket, shared_state).await;
+    if let Err(error) = &result {
+        // tell the peer why the session failed (best effort, the socket may already be gone)
+        let frame = ControlFrame::new(ControlMessage::Error {
+            reason: error.message.clone(),
+        });
+        if let Ok(text) = frame.to_json() {
+            let _ = socket.send(Message::Text(text.into())).await;
+        }
+        let _ = socket
+            .send(Message::Close(Some(CloseFrame {
+                code: error.close_code(),
+                reason: error.close_reason().into(),
+            })))
+            .await;
+    }
+    result
+}
+
+async fn session_processing(
+    socket: &mut WebSocket,
+    shared_state: RwLockSharedState,
+) -> Result<(), AppError> {
+    // the track selected by the open message of this session
+    let mut selected_track: Option<Track> = None;
+
     while let Some(message) = socket.recv().await {
         // Receive a message from the client
         match message {
             Ok(message) => {
                 match message {
                     Message::Text(text) => {
-                        // receive connection request from client
-                        let msg = text.to_string();
-                        if msg != "open" && msg != "accept" {
-                            tracing::info!("Received unexpected text: {:?}", msg);
-                            return Err(HandlerError::UnexpectedMessageError(msg).into());
-                        }
-                        tracing::info!("Received text: {:?}", msg);
+                        // receive control message from client
+                        let frame = ControlFrame::from_json(text.as_str()).inspect_err(|_| {
+                            tracing::info!("Received unexpected text: {:?}", text);
+                        })?;
+                        tracing::info!("Received control message: {:?}", frame);
 
-                        // step1: analyze audio file and send audio info to middle-server
-                        if msg == "open" {
-                            // analyze audio file
-                            let audio_info = crate::application::analyzer::wave_analyzer()?;
-                            // send audio info to middle-server
-                            /*
-                                FORMAT: <channels> <sample_rate> <bits_per_sample> <pcm_format>
-                            */
-                            socket
-                                .send(Message::Text(
-                                    format!(
-                                        "{} {} {} {}",
-                                        audio_info.channels,
-                                        audio_info.sample_rate,
-                                        audio_info.bits_per_sample,
-                                        audio_info.pcm_format
+                        match frame.message {
+                            // step0: answer the greeting with our protocol version
+                            ControlMessage::Hello => {
+                                send_control(socket, ControlMessage::Hello).await?;
+                            }
+                            // step1: analyze audio file and send audio info to middle-server
+                            ControlMessage::Open { track_id, .. } => {
+                                // select track (the default track if no id is given)
+                                let catalog = shared_state.read().await;
+                                let track = match &track_id {
+                                    Some(track_id) => catalog.get(track_id),
+                                    None => catalog.default_track(),
+                                }
+                                .cloned()
+                                .ok_or_else(|| {
+                                    HandlerError::TrackNotFoundError(
+                                        track_id.clone().unwrap_or_default(),
                                     )
-                                    .into(),
-                                ))
-                                .await
-                                .map_err(HandlerError::AxumError)?;
-                        }
+                                })?;
+                                drop(catalog); // release the lock
+                                tracing::info!("Selected track: {:?}", track.id);
 
-                        //step2: receive connection acceptance from middle-server and send PCM data to middle-server
-                        if msg == "accept" {
-                            wave_streamer(&mut socket).await?;
+                                // analyze audio file
+                                let audio_info =
+                                    crate::application::analyzer::wave_analyzer(&track.path)?;
+                                selected_track = Some(track);
+                                // send audio info to middle-server
+                                send_control(socket, ControlMessage::StreamHeader(audio_info))
+                                    .await?;
+                            }
+                            //step2: receive connection acceptance from middle-server and send PCM data to middle-server
+                            ControlMessage::Accept => {
+                                let track = selected_track.as_ref().ok_or_else(|| {
+                                    ProtocolError::UnexpectedMessageError(
+                                        "accept received before open".into(),
+                                    )
+                                })?;
+                                wave_streamer(socket, &track.path).await?;
+                                //step3: tell middle-server that the stream is complete
+                                send_control(
+                                    socket,
+                                    ControlMessage::Bye {
+                                        reason: Some("end of stream".into()),
+                                    },
+                                )
+                                .await?;
+                            }
+                            ControlMessage::Bye { reason } => {
+                                tracing::info!("Client said bye: {:?}", reason);
+                                return Ok(());
+                            }
+                            ControlMessage::Error { reason } => {
+                                tracing::error!("Client reported an error: {}", reason);
+                                return Ok(());
+                            }
+                            ControlMessage::StreamHeader(_) => {
+                                return Err(ProtocolError::UnexpectedMessageError(
+                                    "stream_header is only sent by the server".into(),
+                                )
+                                .into());
+                            }
                         }
                     }
                     Message::Close(close) => {
@@ -74,7 +138,7 @@ pub async fn websocket_processing(mut socket: WebSocket) -> Result<(), AppError>
                     }
                     _ => {
                         tracing::error!("Received unsupported message type from server");
-                        return Err(HandlerError::UnexpectedMessageTypeError.into());
+                        return Err(ProtocolError::UnexpectedMessageTypeError.into());
                     }
                 }
             }
@@ -86,3 +150,12 @@ pub async fn websocket_processing(mut socket: WebSocket) -> Result<(), AppError>
     }
     Ok(())
 }
+
+// send a control message as a JSON text frame
+async fn send_control(socket: &mut WebSocket, message: ControlMessage) -> Result<(), HandlerError> {
+    let text = ControlFrame::new(message).to_json()?;
+    socket
+        .send(Message::Text(text.into()))
+        .await
+        .map_err(HandlerError::AxumError)
+}
@@ -1,5 +1,6 @@
-use crate::{errors::root::RootError, handlers::ws::websocket_handler};
+use crate::{handlers::ws::websocket_handler, models::track::TrackCatalog};
 use axum::{Router, extract::DefaultBodyLimit, routing::get};
+use common::errors::root::RootError;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
@@ -12,16 +13,28 @@ pub mod models;
 // Domain
 const IP_ADDRESS: &str = "localhost";
 const PORT: u16 = 5000;
+// Track catalog
+const DATA_DIR: &str = "data";
 
 #[tokio::main]
 async fn main() -> Result<(), RootError> {
-    // shared object
-    let shared_state = Arc::new(RwLock::new(0));
     // tracing
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
         .with_max_level(tracing::Level::DEBUG)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
+    // track catalog
+    let catalog = TrackCatalog::load(DATA_DIR)?;
+    if catalog.is_empty() {
+        tracing::warn!("No tracks found in {}", DATA_DIR);
+    }
+    tracing::info!(
+        "Loaded {} track(s): {:?}",
+        catalog.len(),
+        catalog.ids().collect::<Vec<_>>()
+    );
+    // shared object
+    let shared_state = Arc::new(RwLock::new(catalog));
     // cors
     let cors = CorsLayer::new().allow_origin(tower_http::cors::Any);
 
@@ -1,2 +1,2 @@
-pub mod audio;
 pub mod shared_state;
+pub mod track;
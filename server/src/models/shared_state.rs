@@ -1,4 +1,5 @@
+use crate::models::track::TrackCatalog;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-pub type RwLockSharedState = Arc<RwLock<i32>>;
+pub type RwLockSharedState = Arc<RwLock<TrackCatalog>>;
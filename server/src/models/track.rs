@@ -0,0 +1,79 @@
+use crate::application::decoder::is_supported;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    /// The identifier a client names in the "open" handshake.
+    ///
+    /// It is the file name without its extension, e.g. `sample3` for `data/sample3.wav`.
+    pub id: String,
+
+    /// The path of the audio file.
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct TrackCatalog {
+    tracks: BTreeMap<String, Track>,
+}
+
+impl TrackCatalog {
+    /// Build the catalog from the audio files found directly under `dir`.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let mut tracks = BTreeMap::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            // skip files the decoder cannot read
+            if !is_supported(&path) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                tracing::warn!("Skipping track with non UTF-8 name: {:?}", path);
+                continue;
+            };
+            if tracks.contains_key(id) {
+                tracing::warn!("Skipping duplicate track id {:?}: {:?}", id, path);
+                continue;
+            }
+            tracks.insert(
+                id.to_string(),
+                Track {
+                    id: id.to_string(),
+                    path,
+                },
+            );
+        }
+
+        Ok(TrackCatalog { tracks })
+    }
+
+    /// Look up a track by its id.
+    pub fn get(&self, id: &str) -> Option<&Track> {
+        self.tracks.get(id)
+    }
+
+    /// The track used when the client does not name one (the first id in sort order).
+    pub fn default_track(&self) -> Option<&Track> {
+        self.tracks.values().next()
+    }
+
+    /// All track ids in sort order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.tracks.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+}